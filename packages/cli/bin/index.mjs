@@ -3,16 +3,22 @@
 // src/index.ts
 import { resolve } from "node:path";
 import { readFile } from "node:fs/promises";
+import { createInterface } from "node:readline/promises";
 import { spawnSync } from "node:child_process";
-import { init, install, collectStagedCommands, collectHookCommands, autoCommit } from "@doremijs/igit-core";
+import { init, detectProject, resolvedConfig, configSchema, validateConfig, migrateConfig, install, uninstall, hooksStatus, syncHooks, runStagedHooks, runHook, autoCommit } from "@doremijs/igit-core";
 var currentPath = new URL(".", import.meta.url).pathname;
 var args = process.argv.slice(2);
 var LOG_PREFIX = "\x1B[33m[iGit]\x1B[0m ";
 var helpMessage = `Usage: igit <command> [options]
 
 Commands:
-  init              Initialize the igit configuration file
+  init [options]    Initialize the igit configuration file (--preset js|rust|python|polyglot, --force, --merge, --install, -y to skip questions)
+  config            Print the config in effect, with the configs it extends merged in (--check to report problems)
+  schema            Print the JSON Schema of the config file
+  migrate           Upgrade the config to the current schema version (-d or --dry-run to only print the changes)
   install           Install hooks to the git repository
+  status            Check the installed hooks match the config, exits with 1 when they do not
+  uninstall         Remove igit hooks and restore the replaced ones
   run [hook] [args] Run a specific hook
   commit [options]  Auto commit with ai (-d or --dry-run to dry run, -y to directly commit)
   version           Print the version`;
@@ -25,6 +31,59 @@ function runCommand(shellCommand) {
     throw new Error(`Command failed with status ${ret.status}`);
   }
 }
+function assertReport(report) {
+  const failed = report.results.find((result) => !result.success);
+  if (failed) {
+    throw new Error(`Command \`${failed.command}\` failed with status ${failed.exitCode ?? "unknown"}`);
+  }
+}
+function printStatus(status) {
+  if (status.foreign.length > 0) {
+    console.log(`${LOG_PREFIX}\x1B[33mWritten by another tool:\x1B[0m ${status.foreign.join(", ")}, run \`igit install\` to back them up and replace them`);
+  }
+  if (status.inSync) {
+    console.log(`${LOG_PREFIX}Hooks are in sync with the config`);
+    return;
+  }
+  for (const [label, hooks] of [["Missing", status.missing], ["Outdated", status.outdated], ["Unused", status.unused]]) {
+    if (hooks.length > 0) {
+      console.log(`${LOG_PREFIX}\x1B[33m${label}:\x1B[0m ${hooks.join(", ")}`);
+    }
+  }
+  console.log(`${LOG_PREFIX}Run \`igit install\` to update the hooks`);
+}
+function printIssues(issues) {
+  if (issues.length === 0) {
+    console.log(`${LOG_PREFIX}No problems found in the config`);
+    return;
+  }
+  for (const issue of issues) {
+    const location = [issue.file, issue.line, issue.column].filter((part) => part !== void 0).join(":");
+    const color = issue.severity === "error" ? "\x1B[31m" : "\x1B[33m";
+    const key = issue.key ? `\`${issue.key}\` ` : "";
+    console.log(`${LOG_PREFIX}${color}${issue.severity}\x1B[0m ${location}: ${key}${issue.message}`);
+  }
+}
+function optionValue(options, name) {
+  const index = options.indexOf(name);
+  return index === -1 ? void 0 : options[index + 1];
+}
+async function promptInit(options, project) {
+  if (project.linters.length > 0) {
+    console.log(`${LOG_PREFIX}Detected linters: ${project.linters.join(", ")}`);
+  }
+  const rl = createInterface({ input: process.stdin, output: process.stdout });
+  try {
+    const preset = (await rl.question(`${LOG_PREFIX}Preset (js, rust, python, polyglot) [${project.preset}]: `)).trim();
+    options.preset = preset || project.preset;
+    if (!options.install) {
+      const answer = (await rl.question(`${LOG_PREFIX}Install the hooks now? (Y/n) `)).trim().toLowerCase();
+      options.install = answer === "" || answer === "y" || answer === "yes";
+    }
+  } finally {
+    rl.close();
+  }
+}
 async function start() {
   if (args.length === 0) {
     console.log(helpMessage);
@@ -33,23 +92,63 @@ async function start() {
   const command = args[0];
   const options = args.slice(1);
   switch (command) {
-    case "init":
-      init();
+    case "init": {
+      const initOptions = {
+        preset: optionValue(options, "--preset"),
+        force: options.includes("--force"),
+        merge: options.includes("--merge"),
+        install: options.includes("--install")
+      };
+      const project = detectProject();
+      const keepsConfig = project.configFile && !initOptions.force && !initOptions.merge;
+      if (process.stdin.isTTY && !initOptions.preset && !options.includes("-y") && !keepsConfig) {
+        await promptInit(initOptions, project);
+      }
+      init(initOptions);
+      break;
+    }
+    case "config": {
+      if (!options.includes("--check")) {
+        console.log(resolvedConfig());
+        break;
+      }
+      const issues = validateConfig();
+      printIssues(issues);
+      if (issues.some((issue) => issue.severity === "error")) {
+        process.exitCode = 1;
+      }
+      break;
+    }
+    case "schema":
+      process.stdout.write(configSchema());
+      break;
+    case "migrate":
+      migrateConfig(options.includes("-d") || options.includes("--dry-run"));
       break;
     case "install":
       install();
       break;
+    case "status": {
+      const status = hooksStatus();
+      printStatus(status);
+      if (!status.inSync) {
+        process.exitCode = 1;
+      }
+      break;
+    }
+    case "uninstall":
+      uninstall();
+      break;
     case "run":
-      if (options[0] === "pre-commit") {
-        const commands = collectStagedCommands();
-        for (const command2 of commands) {
-          runCommand(command2);
-        }
+      try {
+        syncHooks();
+      } catch (error) {
+        console.warn(`${LOG_PREFIX}\x1B[33mFailed to sync hooks: ${error instanceof Error ? error.message : error}\x1B[0m`);
       }
-      const hookCommands = collectHookCommands(options[0], options.slice(1));
-      for (const command2 of hookCommands) {
-        runCommand(command2);
+      if (options[0] === "pre-commit") {
+        assertReport(runStagedHooks());
       }
+      assertReport(runHook(options[0], options.slice(1)));
       break;
     case "commit":
       const commit = options.includes("-y");
@@ -5,7 +5,7 @@ import { fileURLToPath } from 'node:url'
 import { execSync } from 'node:child_process'
 import test from 'ava'
 
-import { init, install, collectHookCommands } from '../index.js'
+import { init, resolvedConfig, configSchema, validateConfig, migrateConfig, install, uninstall, hooksStatus, collectHookCommands, runHook } from '../index.js'
 
 const testDir = path.join(fileURLToPath(import.meta.url), '../../_test')
 if (!fsSync.existsSync(testDir)) {
@@ -51,6 +51,91 @@ test.serial('init from native', async(t) => {
   }
 })
 
+test.serial('init with a preset', async (t) => {
+  t.true(init().written)
+  t.false(init().written)
+  const report = init({ preset: 'rust', force: true })
+  t.is(report.preset, 'rust')
+  t.deepEqual(report.linters, ['rustfmt'])
+  const config = await fs.readFile(path.join(testDir, '.config/igit.yaml'), 'utf-8')
+  t.true(config.includes("'**/*.rs': rustfmt --edition 2021 {files}"))
+})
+
+test.serial('resolve config with extends', async (t) => {
+  try {
+    init()
+    const configPath = path.join(testDir, '.config/igit.yaml')
+    const config = await fs.readFile(configPath, 'utf-8')
+    await fs.writeFile(path.join(testDir, '.config/base.yaml'), 'commit_msg:\n  validTypes: [feat]\n')
+    await fs.writeFile(configPath, `extends: ./base.yaml\n${config}`)
+    t.true(resolvedConfig().includes('- feat'))
+    await fs.writeFile(configPath, config)
+  } catch (err) {
+    t.fail(err.message)
+  } finally {
+    await fs.rm(path.join(testDir, '.config/base.yaml'), { force: true })
+  }
+})
+
+test.serial('config in package.json', async (t) => {
+  const packagePath = path.join(testDir, 'package.json')
+  try {
+    await fs.writeFile(packagePath, JSON.stringify({ name: 'app', igit: { commit_msg: { validTypes: ['feat'] } } }))
+    t.true(resolvedConfig().includes('- feat'))
+  } catch (err) {
+    t.fail(err.message)
+  } finally {
+    await fs.rm(packagePath, { force: true })
+  }
+})
+
+test.serial('config schema', async (t) => {
+  const schema = JSON.parse(configSchema())
+  t.deepEqual(Object.keys(schema.properties).sort(), ['ai', 'commit_msg', 'extends', 'hooks', 'staged_hooks'])
+  init()
+  const config = await fs.readFile(path.join(testDir, '.config/igit.yaml'), 'utf-8')
+  t.true(config.startsWith(`# yaml-language-server: $schema=${schema.$id}`))
+})
+
+test.serial('validate config', async (t) => {
+  try {
+    init()
+    const configPath = path.join(testDir, '.config/igit.yaml')
+    t.deepEqual(validateConfig(), [])
+    await fs.writeFile(configPath, 'stagedHooks:\n  enabled: true\nhooks:\n  enabled: yes please\n')
+    const [error] = validateConfig()
+    t.is(error.severity, 'error')
+    t.is(error.key, 'hooks.enabled')
+    t.is(error.line, 4)
+    await fs.writeFile(configPath, 'stagedHooks:\n  enabled: true\n')
+    const [warning] = validateConfig()
+    t.is(warning.severity, 'warning')
+    t.true(warning.message.includes('`staged_hooks`'))
+  } catch (err) {
+    t.fail(err.message)
+  }
+})
+
+test.serial('migrate config', async (t) => {
+  try {
+    init()
+    const configPath = path.join(testDir, '.config/igit.yaml')
+    await fs.writeFile(configPath, '# yaml-language-server: $schema=https://igit.erguotou.me/schema/0.0.1/schema.json\nstaged_hooks:\n  enabled: true # lint\n')
+    const dryRun = migrateConfig(true)
+    t.is(dryRun.fromVersion, '0.0.1')
+    t.is(dryRun.changes.length, 1)
+    t.false(dryRun.written)
+    const report = migrateConfig()
+    t.true(report.written)
+    const config = await fs.readFile(configPath, 'utf-8')
+    t.true(config.includes(`/schema/${report.toVersion}/`))
+    t.true(config.includes('staged_hooks:\n  enabled: true # lint\n'))
+    t.deepEqual(migrateConfig().changes, [])
+  } catch (err) {
+    t.fail(err.message)
+  }
+})
+
 test.serial('install hooks', async (t) => {
   try {
     init()
@@ -67,6 +152,31 @@ test.serial('install hooks', async (t) => {
   }
 })
 
+test.serial('hooks status', async (t) => {
+  try {
+    init()
+    install()
+    t.true(hooksStatus().inSync)
+    await fs.rm('.git/hooks/pre-commit')
+    t.deepEqual(hooksStatus().missing, ['pre-commit'])
+  } catch (err) {
+    t.fail(err.message)
+  }
+})
+
+test.serial('uninstall hooks', async (t) => {
+  try {
+    init()
+    install()
+    const report = uninstall()
+    t.true(report.removed.includes('pre-commit'))
+    t.true(report.removed.includes('commit-msg'))
+    await t.throwsAsync(fs.access('.git/hooks/pre-commit'))
+  } catch (err) {
+    t.fail(err.message)
+  }
+})
+
 test.serial('run hook', async (t) => {
   try {
     init()
@@ -84,3 +194,39 @@ test.serial('run hook', async (t) => {
     await fs.rm(path.join(testDir, 'pre-push.txt'), { force: true })
   }
 })
+
+test.serial('run hook natively', async (t) => {
+  try {
+    init()
+    const configPath = path.join(testDir, '.config/igit.yaml')
+    const config = await fs.readFile(configPath, 'utf-8')
+    await fs.writeFile(configPath, config.replace('hooks: {}', 'hooks: \n    pre-push: printf "hello" > pre-push.txt'))
+    const report = runHook('pre-push', [])
+    t.true(report.success)
+    t.is(report.results[0].exitCode, 0)
+    const prePushResult = await fs.readFile(path.join(testDir, 'pre-push.txt'), 'utf-8')
+    t.is(prePushResult, 'hello')
+  } catch (err) {
+    t.fail(err.message)
+  } finally {
+    await fs.rm(path.join(testDir, 'pre-push.txt'), { force: true })
+  }
+})
+
+test.serial('lint commit message', async (t) => {
+  const messagePath = path.join(testDir, 'COMMIT_EDITMSG')
+  try {
+    init()
+    const configPath = path.join(testDir, '.config/igit.yaml')
+    await fs.writeFile(configPath, 'commit_msg:\n  enabled: true\n  rules:\n    headerMaxLength: 30\n    noTrailingPeriod: warning\n    scopeRequired: error\n')
+    await fs.writeFile(messagePath, 'feat(core): add a rule engine\n')
+    t.deepEqual(runHook('commit-msg', [messagePath]).results, [])
+    await fs.writeFile(messagePath, 'feat: add a rule engine for commit messages.\n')
+    const error = t.throws(() => runHook('commit-msg', [messagePath]))
+    t.is(error.message, 'Invalid commit message, 2 errors found')
+  } catch (err) {
+    t.fail(err.message)
+  } finally {
+    await fs.rm(messagePath, { force: true })
+  }
+})
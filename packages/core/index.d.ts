@@ -6,9 +6,105 @@
 export interface ShellCommand {
   command: string
   args?: string[]
+  /** directory the command runs in, the current directory when not set */
+  cwd?: string
 }
-export declare function init(): void
+export interface CommandResult {
+  command: string
+  args?: string[]
+  /** `undefined` when the process was killed by a signal or failed to spawn */
+  exitCode?: number
+  success: boolean
+  stdout: string
+  stderr: string
+  durationMs: number
+}
+export interface RunReport {
+  success: boolean
+  results: Array<CommandResult>
+  /** wall-clock time of the whole run, commands running in parallel overlap */
+  durationMs: number
+}
+export interface InitOptions {
+  /** `js`, `rust`, `python` or `polyglot`, detected from the project when not set */
+  preset?: string
+  /** overwrite an existing config */
+  force?: boolean
+  /** merge the generated config into an existing one, existing values win */
+  merge?: boolean
+  /** install the hooks afterwards */
+  install?: boolean
+}
+export interface ProjectInfo {
+  preset: string
+  /** linters set up in the project */
+  linters: Array<string>
+  /** config already in the directory, relative to it */
+  configFile?: string
+}
+export interface InitReport {
+  configFile: string
+  preset: string
+  /** linters the staged rules were generated for */
+  linters: Array<string>
+  /** false when a config already existed and was kept */
+  written: boolean
+}
+export interface HooksStatus {
+  /** whether the installed hooks match the config, hooks of other tools are not counted */
+  inSync: boolean
+  /** configured hooks that are not installed */
+  missing: Array<string>
+  /** configured hooks written by another tool, `igit install` backs them up and replaces them */
+  foreign: Array<string>
+  /** igit hooks installed from an older config */
+  outdated: Array<string>
+  /** igit hooks that are no longer configured */
+  unused: Array<string>
+}
+export interface UninstallReport {
+  /** igit hooks that were deleted */
+  removed: Array<string>
+  /** backed up hooks moved back in place */
+  restored: Array<string>
+  /** older backups left in the hooks dir, only the most recent backup of a hook is restored */
+  keptBackups: Array<string>
+}
+export interface ConfigIssue {
+  /** config file the issue was found in */
+  file: string
+  /** one-based line of the issue when known */
+  line?: number
+  /** one-based column of the issue when known */
+  column?: number
+  /** dotted path of the offending key, like `staged_hooks.concurrency` */
+  key?: string
+  message: string
+  /** `error` or `warning` */
+  severity: string
+}
+export interface MigrateReport {
+  configFile: string
+  /** schema version the config referenced, `undefined` when it did not reference one */
+  fromVersion?: string
+  toVersion: string
+  /** what was rewritten, empty when the config is up to date */
+  changes: Array<string>
+  /** false for dry runs and configs that are up to date */
+  written: boolean
+}
+export declare function init(options?: InitOptions | undefined | null): InitReport
+export declare function detectProject(): ProjectInfo
+export declare function resolvedConfig(): string
+export declare function configSchema(): string
+export declare function validateConfig(): Array<ConfigIssue>
+export declare function migrateConfig(dryRun?: boolean | undefined | null): MigrateReport
 export declare function install(): void
+export declare function hooksStatus(): HooksStatus
+export declare function syncHooks(): Array<string>
+export declare function uninstall(): UninstallReport
 export declare function collectStagedCommands(): Array<ShellCommand>
 export declare function collectHookCommands(hookName: string, args: Array<string>): Array<ShellCommand>
+export declare function runStagedHooks(): RunReport
+export declare function runHook(hookName: string, args: Array<string>): RunReport
 export declare function autoCommit(): Promise<string>
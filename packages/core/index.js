@@ -310,10 +310,20 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { init, install, collectStagedCommands, collectHookCommands, autoCommit } = nativeBinding
+const { init, detectProject, resolvedConfig, configSchema, validateConfig, migrateConfig, install, hooksStatus, syncHooks, uninstall, collectStagedCommands, collectHookCommands, runStagedHooks, runHook, autoCommit } = nativeBinding
 
 module.exports.init = init
+module.exports.detectProject = detectProject
+module.exports.resolvedConfig = resolvedConfig
+module.exports.configSchema = configSchema
+module.exports.validateConfig = validateConfig
+module.exports.migrateConfig = migrateConfig
 module.exports.install = install
+module.exports.hooksStatus = hooksStatus
+module.exports.syncHooks = syncHooks
+module.exports.uninstall = uninstall
 module.exports.collectStagedCommands = collectStagedCommands
 module.exports.collectHookCommands = collectHookCommands
+module.exports.runStagedHooks = runStagedHooks
+module.exports.runHook = runHook
 module.exports.autoCommit = autoCommit
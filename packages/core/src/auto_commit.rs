@@ -85,7 +85,7 @@ pub async fn generate_commit_message() -> Result<String, String> {
     .map_err(|e| format!("Failed to parse response: {}", e))?;
 
   spinner.stop();
-  println!("");
+  println!();
 
   let content = response_json["choices"][0]["message"]["content"]
     .as_str()
@@ -101,7 +101,7 @@ pub async fn generate_commit_message() -> Result<String, String> {
 
 fn get_git_diff() -> Result<String, String> {
   let output = Command::new("git")
-    .args(&[
+    .args([
       "diff",
       "--staged",
       "--ignore-all-space",
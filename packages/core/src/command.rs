@@ -1,4 +1,8 @@
+use crate::git::path_bytes;
+use std::collections::BTreeSet;
+use std::ffi::{OsStr, OsString};
 use std::fmt::{Display, Formatter, Result};
+use std::path::{Component, Path, PathBuf};
 
 #[derive(Debug)]
 #[napi(object)]
@@ -7,6 +11,8 @@ pub struct ShellCommand {
   pub command: String,
   #[napi(ts_type = "string[]")]
   pub args: Option<Vec<String>>,
+  /// directory the command runs in, the current directory when not set
+  pub cwd: Option<String>,
 }
 
 impl ShellCommand {
@@ -14,6 +20,7 @@ impl ShellCommand {
     Self {
       command: command.into(),
       args: None,
+      cwd: None,
     }
   }
 
@@ -21,6 +28,20 @@ impl ShellCommand {
     Self {
       command: command.into(),
       args: Some(args.into_iter().map(|s| s.into()).collect()),
+      cwd: None,
+    }
+  }
+
+  /**
+   * full command line passed to the shell, args are quoted
+   */
+  pub fn to_command_line(&self) -> String {
+    match &self.args {
+      Some(args) if !args.is_empty() => {
+        let quoted = args.iter().map(|arg| quote_arg(arg)).collect::<Vec<String>>();
+        format!("{} {}", self.command, quoted.join(" "))
+      }
+      _ => self.command.clone(),
     }
   }
 }
@@ -33,3 +54,311 @@ impl Display for ShellCommand {
     }
   }
 }
+
+/**
+ * quote a single argument for the platform shell
+ */
+pub fn quote_arg(arg: &str) -> String {
+  let is_safe = !arg.is_empty()
+    && arg
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || "-_./:=+@%,".contains(c));
+  if is_safe {
+    return arg.to_string();
+  }
+  if cfg!(windows) {
+    format!("\"{}\"", arg.replace('"', "\"\""))
+  } else {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+  }
+}
+
+/**
+ * quote a path for the platform shell, file names that are not valid UTF-8 are
+ * written as `printf` escapes so the shell gets the exact bytes back
+ */
+pub fn quote_path(path: &OsStr) -> String {
+  if let Some(path) = path.to_str() {
+    return quote_arg(path);
+  }
+  let escaped = path_bytes(path)
+    .iter()
+    .map(|&b| match b {
+      b if b.is_ascii_alphanumeric() || b"-_./".contains(&b) => (b as char).to_string(),
+      b => format!("\\{:03o}", b),
+    })
+    .collect::<String>();
+  format!("\"$(printf '{}')\"", escaped)
+}
+
+/**
+ * max length of a command line passed to the shell, half of the platform
+ * limit to leave room for the environment
+ */
+pub fn default_max_arg_length() -> usize {
+  let limit = if cfg!(windows) {
+    8191
+  } else if cfg!(target_os = "macos") {
+    262144
+  } else {
+    131072
+  };
+  limit / 2
+}
+
+/**
+ * split items into batches so that `base_length` plus the cost of each item in a
+ * batch fits in `max_length`, every batch holds at least one item
+ */
+pub fn chunk_by_cost<T, F>(base_length: usize, items: &[T], cost: F, max_length: usize) -> Vec<Vec<T>>
+where
+  T: Clone,
+  F: Fn(&T) -> usize,
+{
+  let mut batches: Vec<Vec<T>> = vec![];
+  let mut batch: Vec<T> = vec![];
+  let mut length = base_length;
+  for item in items {
+    let item_length = cost(item);
+    if !batch.is_empty() && length + item_length > max_length {
+      batches.push(std::mem::take(&mut batch));
+      length = base_length;
+    }
+    batch.push(item.clone());
+    length += item_length;
+  }
+  if !batch.is_empty() {
+    batches.push(batch);
+  }
+  batches
+}
+
+/**
+ * split args into batches so that `command` plus each batch fits in `max_length`
+ */
+pub fn chunk_args<S: AsRef<OsStr> + Clone>(command: &str, args: &[S], max_length: usize) -> Vec<Vec<S>> {
+  chunk_by_cost(command.len(), args, |arg| quote_path(arg.as_ref()).len() + 1, max_length)
+}
+
+static PLACEHOLDERS: &[&str] = &["{files}", "{file}", "{dirs}", "{relative}", "{absolute}"];
+
+/**
+ * where file paths are resolved from, paths given to placeholders are relative to `root`
+ */
+pub struct PathContext<'a> {
+  pub root: &'a Path,
+  pub cwd: &'a Path,
+}
+
+impl PathContext<'_> {
+  /**
+   * path of a root relative file as seen from `cwd`
+   */
+  pub fn rebase(&self, file: &OsStr) -> OsString {
+    if self.root == self.cwd {
+      return file.to_os_string();
+    }
+    relative_path(self.cwd, &self.root.join(file)).into_os_string()
+  }
+}
+
+pub fn has_placeholders(command: &str) -> bool {
+  PLACEHOLDERS.iter().any(|p| command.contains(p))
+}
+
+/**
+ * `{file}` runs the command once per file
+ */
+pub fn runs_per_file(command: &str) -> bool {
+  command.contains("{file}")
+}
+
+/**
+ * path of `to` relative to the directory `from`, both absolute
+ */
+pub fn relative_path(from: &Path, to: &Path) -> PathBuf {
+  let from = from.components().collect::<Vec<Component>>();
+  let to = to.components().collect::<Vec<Component>>();
+  let common = from.iter().zip(to.iter()).take_while(|(a, b)| a == b).count();
+  let mut path = PathBuf::new();
+  for _ in common..from.len() {
+    path.push("..");
+  }
+  for component in &to[common..] {
+    path.push(component.as_os_str());
+  }
+  if path.as_os_str().is_empty() {
+    path.push(".");
+  }
+  path
+}
+
+fn parent_dir(file: &OsStr) -> OsString {
+  match Path::new(file).parent() {
+    Some(parent) if !parent.as_os_str().is_empty() => parent.as_os_str().to_os_string(),
+    _ => OsString::from("."),
+  }
+}
+
+fn placeholder_values(placeholder: &str, files: &[OsString], context: &PathContext) -> Vec<OsString> {
+  match placeholder {
+    "{files}" | "{file}" | "{relative}" => files.iter().map(|file| context.rebase(file)).collect(),
+    "{dirs}" => files
+      .iter()
+      .map(|file| parent_dir(&context.rebase(file)))
+      .collect::<BTreeSet<OsString>>()
+      .into_iter()
+      .collect(),
+    "{absolute}" => files
+      .iter()
+      .map(|file| context.root.join(file).into_os_string())
+      .collect(),
+    _ => vec![],
+  }
+}
+
+/**
+ * replace the placeholders of `command` with the quoted files
+ */
+pub fn render_placeholders(command: &str, files: &[OsString], context: &PathContext) -> String {
+  // a single pass over the template, so placeholders inside file names are left alone
+  let mut rendered = String::with_capacity(command.len());
+  let mut rest = command;
+  while let Some(start) = rest.find('{') {
+    rendered.push_str(&rest[..start]);
+    rest = &rest[start..];
+    match PLACEHOLDERS.iter().find(|placeholder| rest.starts_with(*placeholder)) {
+      Some(placeholder) => {
+        let values = placeholder_values(placeholder, files, context)
+          .iter()
+          .map(|value| quote_path(value))
+          .collect::<Vec<String>>();
+        rendered.push_str(&values.join(" "));
+        rest = &rest[placeholder.len()..];
+      }
+      None => {
+        rendered.push('{');
+        rest = &rest[1..];
+      }
+    }
+  }
+  rendered.push_str(rest);
+  rendered
+}
+
+/**
+ * how much a single file adds to the rendered command line
+ */
+pub fn placeholder_cost(command: &str, file: &OsStr, context: &PathContext) -> usize {
+  let files = [file.to_os_string()];
+  PLACEHOLDERS
+    .iter()
+    .map(|placeholder| {
+      let count = command.matches(placeholder).count();
+      if count == 0 {
+        return 0;
+      }
+      let length = placeholder_values(placeholder, &files, context)
+        .iter()
+        .map(|value| quote_path(value).len() + 1)
+        .sum::<usize>();
+      count * length
+    })
+    .sum()
+}
+
+/* ------------ test ------------ */
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_quote_arg() {
+    assert_eq!(quote_arg("src/main.rs"), "src/main.rs");
+    if !cfg!(windows) {
+      assert_eq!(quote_arg("my file.ts"), "'my file.ts'");
+      assert_eq!(quote_arg("it's.ts"), "'it'\\''s.ts'");
+    }
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_quote_non_utf8_path() {
+    use std::os::unix::ffi::OsStrExt;
+    let path = OsStr::from_bytes(b"my bad\xff'.ts");
+    let quoted = quote_path(path);
+    assert_eq!(quoted, "\"$(printf 'my\\040bad\\377\\047.ts')\"");
+    let output = std::process::Command::new("sh")
+      .arg("-c")
+      .arg(format!("printf %s {}", quoted))
+      .output()
+      .unwrap();
+    assert_eq!(output.stdout, path.as_bytes());
+  }
+
+  #[test]
+  fn test_chunk_args() {
+    let files = vec!["aaaa", "bbbb", "cccc", "dddd", "eeeeeeeeeeeeeeeeeeee"];
+    // "lint" + " aaaa" + " bbbb" = 14
+    let batches = chunk_args("lint", &files, 14);
+    assert_eq!(
+      batches,
+      vec![
+        vec!["aaaa", "bbbb"],
+        vec!["cccc", "dddd"],
+        vec!["eeeeeeeeeeeeeeeeeeee"],
+      ]
+    );
+    assert_eq!(chunk_args("lint", &files, 1000).len(), 1);
+    assert!(chunk_args("lint", &Vec::<String>::new(), 1000).is_empty());
+  }
+
+  #[test]
+  fn test_relative_path() {
+    let root = Path::new("/repo");
+    assert_eq!(relative_path(root, Path::new("/repo/src/a.ts")), PathBuf::from("src/a.ts"));
+    assert_eq!(
+      relative_path(Path::new("/repo/packages/app"), Path::new("/repo/src/a.ts")),
+      PathBuf::from("../../src/a.ts")
+    );
+    assert_eq!(relative_path(root, root), PathBuf::from("."));
+  }
+
+  #[test]
+  fn test_render_placeholders() {
+    let context = PathContext {
+      root: Path::new("/repo"),
+      cwd: Path::new("/repo/packages"),
+    };
+    let files = vec![OsString::from("src/a.ts"), OsString::from("src/b.ts"), OsString::from("c.ts")];
+    assert_eq!(
+      render_placeholders("prettier --write {files} --log-level warn", &files, &context),
+      "prettier --write ../src/a.ts ../src/b.ts ../c.ts --log-level warn"
+    );
+    let root_context = PathContext {
+      root: Path::new("/repo"),
+      cwd: Path::new("/repo"),
+    };
+    assert_eq!(render_placeholders("ls {dirs}", &files, &root_context), "ls . src");
+    assert_eq!(root_context.rebase(OsStr::new("src/a.ts")), "src/a.ts");
+    assert_eq!(
+      render_placeholders("eslint {relative}", &files[..1], &context),
+      "eslint ../src/a.ts"
+    );
+    assert_eq!(
+      render_placeholders("cat {absolute}", &files[..1], &context),
+      "cat /repo/src/a.ts"
+    );
+    assert_eq!(render_placeholders("ls {dirs}", &files, &context), "ls .. ../src");
+    if !cfg!(windows) {
+      let files = vec![OsString::from("{absolute} {files}.ts"), OsString::from("{dirs}/b.ts")];
+      assert_eq!(
+        render_placeholders("eslint {files} && ls {dirs}", &files, &root_context),
+        "eslint '{absolute} {files}.ts' '{dirs}/b.ts' && ls . '{dirs}'"
+      );
+    }
+    assert!(has_placeholders("cat {file}"));
+    assert!(runs_per_file("cat {file}"));
+    assert!(!has_placeholders("tsc --noEmit -p ."));
+  }
+}
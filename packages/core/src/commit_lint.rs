@@ -0,0 +1,280 @@
+use crate::config::{CommitLintConfig, DescriptionCase, RuleLevel};
+use crate::hooks::{CommitMessage, DEFAULT_VALID_TYPES};
+use crate::log::LOG_PREFIX;
+
+static DEFAULT_MAX_LENGTH: usize = 100;
+/// the line `git commit -v` puts above the diff, after the comment char
+static SCISSORS: &str = " ------------------------ >8 ------------------------";
+/// comment chars git picks from when `core.commentChar` is `auto`
+static AUTO_COMMENT_CHARS: &str = "#;@!$%^&|:";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+  /// config key of the rule, like `headerMaxLength`
+  pub rule: &'static str,
+  pub level: RuleLevel,
+  pub message: String,
+}
+
+/**
+ * whether `word` is in `text` on its own, ignoring case
+ */
+fn contains_word(text: &str, word: &str) -> bool {
+  let text = text.to_lowercase();
+  let word = word.to_lowercase();
+  if word.is_empty() {
+    return false;
+  }
+  text.match_indices(&word).any(|(start, _)| {
+    let before = text[..start].chars().next_back();
+    let after = text[start + word.len()..].chars().next();
+    !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+  })
+}
+
+/**
+ * the comment char git picked for `auto`, the one starting the scissors line
+ * or the status comments at the end of the message
+ */
+fn auto_comment_char(message: &str) -> String {
+  message
+    .lines()
+    .find(|line| line.get(1..) == Some(SCISSORS))
+    .or_else(|| message.lines().rev().find(|line| !line.trim().is_empty()))
+    .and_then(|line| line.chars().next())
+    .filter(|c| AUTO_COMMENT_CHARS.contains(*c))
+    .unwrap_or('#')
+    .to_string()
+}
+
+/**
+ * the message git will commit for the `cleanup` mode of `git::commit_cleanup`: `strip` cuts it
+ * at the scissors line of `git commit -v` and drops the lines starting with `comment_char`,
+ * which is `core.commentChar`, `scissors` only cuts it and the other modes keep every line
+ */
+pub fn clean_message(message: &str, cleanup: &str, comment_char: &str) -> String {
+  let comment_char = match comment_char {
+    "auto" => auto_comment_char(message),
+    comment_char => comment_char.to_string(),
+  };
+  let scissors = format!("{}{}", comment_char, SCISSORS);
+  let cut = matches!(cleanup, "strip" | "scissors");
+  message
+    .lines()
+    .take_while(|line| !cut || *line != scissors)
+    .filter(|line| cleanup != "strip" || !line.starts_with(&comment_char))
+    .collect::<Vec<&str>>()
+    .join("\n")
+    .trim()
+    .to_string()
+}
+
+/**
+ * check a commit message against the type and the rules of the config,
+ * `message` is the message `commit_message` was parsed from, without comments
+ */
+pub fn lint(message: &str, commit_message: &CommitMessage, config: &CommitLintConfig) -> Vec<Violation> {
+  let rules = &config.rules;
+  let lines = message.lines().collect::<Vec<&str>>();
+  let mut violations = vec![];
+  let mut push = |rule: &'static str, level: RuleLevel, message: String| violations.push(Violation { rule, level, message });
+
+  let valid_types = match &config.valid_types {
+    Some(valid_types) => valid_types.to_vec(),
+    None => DEFAULT_VALID_TYPES.iter().map(|s| s.to_string()).collect(),
+  };
+  if !valid_types.contains(&commit_message.commit_type) {
+    push(
+      "validTypes",
+      RuleLevel::Error,
+      format!("type `{}` is not one of {}", commit_message.commit_type, valid_types.join(", ")),
+    );
+  }
+
+  let header = lines.first().copied().unwrap_or_default();
+  if let Some((level, max)) = rules.header_max_length.as_ref().and_then(|rule| rule.resolve(DEFAULT_MAX_LENGTH)) {
+    let length = header.chars().count();
+    if length > max {
+      push("headerMaxLength", level, format!("header is {} characters long, the max is {}", length, max));
+    }
+  }
+
+  let description = &commit_message.description;
+  if let Some((level, case)) = rules.description_case.as_ref().and_then(|rule| rule.resolve(DescriptionCase::Lower)) {
+    let first = description.chars().find(|c| c.is_alphabetic());
+    let message = match case {
+      DescriptionCase::Lower if first.is_some_and(char::is_uppercase) => Some("description must start with a lower-case letter"),
+      DescriptionCase::Sentence if first.is_some_and(char::is_lowercase) => Some("description must start with an upper-case letter"),
+      DescriptionCase::Upper if description.chars().any(char::is_lowercase) => Some("description must be upper-case"),
+      _ => None,
+    };
+    if let Some(message) = message {
+      push("descriptionCase", level, message.to_string());
+    }
+  }
+  if let Some(level) = rules.no_trailing_period.filter(|level| *level != RuleLevel::Off) {
+    if description.trim_end().ends_with('.') {
+      push("noTrailingPeriod", level, "description must not end with a period".to_string());
+    }
+  }
+
+  let scope = commit_message.scope.as_deref().filter(|scope| !scope.is_empty());
+  if let Some((level, scopes)) = rules.valid_scopes.as_ref().and_then(|rule| rule.resolve(vec![])) {
+    if let Some(scope) = scope.filter(|scope| !scopes.is_empty() && !scopes.iter().any(|s| s == scope)) {
+      push("validScopes", level, format!("scope `{}` is not one of {}", scope, scopes.join(", ")));
+    }
+  }
+  if let Some(level) = rules.scope_required.filter(|level| *level != RuleLevel::Off) {
+    if scope.is_none() {
+      push("scopeRequired", level, "scope is required, like `feat(scope): ...`".to_string());
+    }
+  }
+
+  let is_blank = |index: usize| lines.get(index).is_some_and(|line| line.trim().is_empty());
+  if let Some(level) = rules.body_leading_blank.filter(|level| *level != RuleLevel::Off) {
+    if commit_message.body.is_some() && !is_blank(1) {
+      push("bodyLeadingBlank", level, "body must be separated from the header by a blank line".to_string());
+    }
+  }
+  if let Some(level) = rules.footer_leading_blank.filter(|level| *level != RuleLevel::Off) {
+    if commit_message.footer_line.is_some_and(|footer_line| !is_blank(footer_line - 1)) {
+      push("footerLeadingBlank", level, "footers must be separated from the lines above by a blank line".to_string());
+    }
+  }
+
+  if let Some((level, max)) = rules.max_line_length.as_ref().and_then(|rule| rule.resolve(DEFAULT_MAX_LENGTH)) {
+    for (index, line) in lines.iter().enumerate().skip(1) {
+      let length = line.chars().count();
+      if length > max {
+        push("maxLineLength", level, format!("line {} is {} characters long, the max is {}", index + 1, length, max));
+      }
+    }
+  }
+
+  if let Some((level, words)) = rules.forbidden_words.as_ref().and_then(|rule| rule.resolve(vec![])) {
+    for word in words.iter().filter(|word| contains_word(message, word)) {
+      push("forbiddenWords", level, format!("`{}` is not allowed in the commit message", word));
+    }
+  }
+  violations
+}
+
+/**
+ * print the violations, fails when any of them is an error
+ */
+pub fn report(violations: &[Violation]) -> Result<(), String> {
+  for violation in violations {
+    let (color, level) = match violation.level {
+      RuleLevel::Error => ("\x1b[31m", "error"),
+      _ => ("\x1b[33m", "warning"),
+    };
+    println!(
+      "{}{}{}\x1b[0m {} \x1b[90m{}\x1b[0m",
+      LOG_PREFIX, color, level, violation.message, violation.rule
+    );
+  }
+  let errors = violations.iter().filter(|violation| violation.level == RuleLevel::Error).count();
+  match errors {
+    0 => Ok(()),
+    1 => Err("Invalid commit message, 1 error found".to_string()),
+    _ => Err(format!("Invalid commit message, {} errors found", errors)),
+  }
+}
+
+/* ------------ test ------------ */
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::hooks::parse_commit_message;
+
+  fn lint_str(message: &str, config: &str) -> Vec<(&'static str, RuleLevel)> {
+    let config: CommitLintConfig = serde_yaml::from_str(config).unwrap();
+    let message = clean_message(message, "strip", "#");
+    let commit_message = parse_commit_message(&message).unwrap();
+    lint(&message, &commit_message, &config)
+      .into_iter()
+      .map(|violation| (violation.rule, violation.level))
+      .collect()
+  }
+
+  #[test]
+  fn test_lint_defaults() {
+    assert!(lint_str("feat: add Polish language", "enabled: true").is_empty());
+    assert!(lint_str("fix: prevent racing\n\nDismiss old responses.\n\nRefs: #123", "enabled: true").is_empty());
+    assert_eq!(lint_str("feature: add Polish language", "enabled: true"), vec![("validTypes", RuleLevel::Error)]);
+    assert!(lint_str("feature: add Polish language", "validTypes: [feature]").is_empty());
+    // the blank line rules are off until they are set, like the others
+    assert!(lint_str("fix: prevent racing\nDismiss old responses.\nNote: it happens on slow networks", "enabled: true").is_empty());
+    assert_eq!(
+      lint_str(
+        "fix: prevent racing\nDismiss old responses.\nRefs: #123",
+        "rules:\n  bodyLeadingBlank: warning\n  footerLeadingBlank: error"
+      ),
+      vec![("bodyLeadingBlank", RuleLevel::Warning), ("footerLeadingBlank", RuleLevel::Error)]
+    );
+    assert!(lint_str("fix: prevent racing\nRefs: #123", "rules:\n  footerLeadingBlank: off").is_empty());
+  }
+
+  #[test]
+  fn test_lint_rules() {
+    let config = "rules:
+  headerMaxLength: 20
+  descriptionCase: warning
+  noTrailingPeriod: error
+  validScopes: { level: warning, value: [core, cli] }
+  scopeRequired: error
+  maxLineLength: { level: error, value: 10 }
+  forbiddenWords: [wip, fixup]
+";
+    assert!(lint_str("feat(core): add it", config).is_empty());
+    assert_eq!(
+      lint_str("feat(doc): Add WIP support.\n\nshort\nway too long line\n# a commented out line\n\nRefs: #1", config),
+      vec![
+        ("headerMaxLength", RuleLevel::Error),
+        ("descriptionCase", RuleLevel::Warning),
+        ("noTrailingPeriod", RuleLevel::Error),
+        ("validScopes", RuleLevel::Warning),
+        ("maxLineLength", RuleLevel::Error),
+        ("forbiddenWords", RuleLevel::Error),
+      ]
+    );
+    assert_eq!(lint_str("feat: add it", config), vec![("scopeRequired", RuleLevel::Error)]);
+    // whole words only
+    assert!(lint_str("fix(cli): wipe cache", config).is_empty());
+    assert_eq!(
+      lint_str("feat: ADD IT", "rules:\n  descriptionCase: { level: error, value: upper }\n  headerMaxLength: off"),
+      vec![]
+    );
+    assert_eq!(
+      lint_str("feat: add it", "rules:\n  descriptionCase: sentence"),
+      vec![("descriptionCase", RuleLevel::Error)]
+    );
+  }
+
+  #[test]
+  fn test_clean_message() {
+    let verbose = "feat: add it\n\nBody\n# Please enter the commit message\n# ------------------------ >8 ------------------------\n# Do not modify or remove the line above.\ndiff --git a/a.rs b/a.rs\n+fn main() {}\n";
+    assert_eq!(clean_message(verbose, "strip", "#"), "feat: add it\n\nBody");
+    let config = "rules:\n  maxLineLength: { level: error, value: 10 }\n  forbiddenWords: [main]";
+    assert!(lint_str(verbose, config).is_empty());
+
+    let message = "fix: close #1\n\n#2 is fixed too\n; Please enter the commit message\n; ------------------------ >8 ------------------------\n+a\n";
+    assert_eq!(clean_message(message, "strip", ";"), "fix: close #1\n\n#2 is fixed too");
+    assert_eq!(clean_message(message, "strip", "auto"), "fix: close #1\n\n#2 is fixed too");
+    assert_eq!(clean_message("fix: it\n\n#2 too\n\n; Changes to be committed:\n;\tmodified: a\n", "strip", "auto"), "fix: it\n\n#2 too");
+    assert_eq!(clean_message("fix: it\n# status\n", "strip", "auto"), "fix: it");
+
+    // `git commit -m` keeps the lines starting with the comment char
+    assert_eq!(clean_message("fix: it\n\n# 2 too\n", "whitespace", "#"), "fix: it\n\n# 2 too");
+    assert_eq!(clean_message("fix: it\n# 2\n", "verbatim", "#"), "fix: it\n# 2");
+    assert_eq!(clean_message(verbose, "scissors", "#"), "feat: add it\n\nBody\n# Please enter the commit message");
+  }
+
+  #[test]
+  fn test_report() {
+    let warning = Violation { rule: "scopeRequired", level: RuleLevel::Warning, message: "scope is required".to_string() };
+    let error = Violation { level: RuleLevel::Error, ..warning.clone() };
+    assert!(report(std::slice::from_ref(&warning)).is_ok());
+    assert_eq!(report(&[warning, error.clone(), error]).unwrap_err(), "Invalid commit message, 2 errors found");
+  }
+}
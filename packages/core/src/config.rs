@@ -1,45 +1,316 @@
+use crate::diagnostic::{self, ConfigIssue};
+use crate::extends;
+use crate::format;
 use crate::git;
+use crate::git::ChangeKind;
 use dirs_next::home_dir;
+use indexmap::IndexMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use serde_yaml;
+use serde_yaml::{Mapping, Value};
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
-#[derive(Serialize, Deserialize, Debug, Clone)]
+use std::path::{Path, PathBuf};
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(untagged)]
 pub enum HookCommand {
   Single(String),
   Multiple(Vec<String>),
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[schemars(deny_unknown_fields)]
 pub struct HooksConfig {
+  /// install and run the configured git hooks
   #[serde(default)]
   pub enabled: bool,
+  /// commands to run for each git hook
   #[serde(default)]
+  #[schemars(schema_with = "crate::schema::hooks_schema")]
   pub hooks: HashMap<String, HookCommand>,
+  /// command used by the installed hooks to start igit, a standalone binary launches itself and the npm package is detected from the lockfile by default
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub launcher: Option<String>,
+  /// when to run the hook igit replaced and `<hook>.local` relative to the igit commands
+  #[serde(default)]
+  pub chain: HookChain,
+  /// reinstall the hooks before running one when they no longer match the config
+  #[serde(default = "default_true", rename = "autoSync")]
+  pub auto_sync: bool,
+}
+
+impl Default for HooksConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      hooks: HashMap::new(),
+      launcher: None,
+      chain: HookChain::default(),
+      auto_sync: true,
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HookChain {
+  #[default]
+  Before,
+  After,
+  Off,
+}
+
+fn default_true() -> bool {
+  true
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct StagedRuleOptions {
+  /// commands to run with the matched files
+  pub commands: HookCommand,
+  /// add files modified by the commands back to the index
+  #[serde(default = "default_true")]
+  pub restage: bool,
+  /// append the matched files to commands without placeholders
+  #[serde(default = "default_true", rename = "passFiles")]
+  pub pass_files: bool,
+  /// rules with a lower priority run first, rules with the same priority keep the config order
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub priority: Option<i32>,
+  /// files matching any of these patterns are skipped
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub ignore: Vec<String>,
+  /// only match files inside this directory, patterns are relative to it
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub dir: Option<String>,
+  /// kinds of change to match, defaults to everything but deletions
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub changes: Option<Vec<ChangeKind>>,
+}
+
+impl StagedRuleOptions {
+  pub fn new(commands: HookCommand) -> Self {
+    Self {
+      commands,
+      restage: true,
+      pass_files: true,
+      priority: None,
+      ignore: vec![],
+      dir: None,
+      changes: None,
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(untagged)]
+pub enum StagedRule {
+  Commands(HookCommand),
+  Options(StagedRuleOptions),
+}
+
+impl From<&StagedRule> for StagedRuleOptions {
+  fn from(rule: &StagedRule) -> Self {
+    match rule {
+      StagedRule::Commands(commands) => StagedRuleOptions::new(commands.clone()),
+      StagedRule::Options(options) => options.clone(),
+    }
+  }
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(untagged)]
+pub enum GlobPatterns {
+  Single(String),
+  Multiple(Vec<String>),
+}
+
+impl GlobPatterns {
+  pub fn to_vec(&self) -> Vec<String> {
+    match self {
+      GlobPatterns::Single(pattern) => vec![pattern.to_string()],
+      GlobPatterns::Multiple(patterns) => patterns.to_vec(),
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[schemars(transform = crate::schema::rule_entry_transform)]
+pub struct StagedRuleEntry {
+  /// glob patterns of the files to match, patterns starting with `!` exclude files
+  #[serde(alias = "patterns")]
+  pub pattern: GlobPatterns,
+  #[serde(flatten)]
+  pub options: StagedRuleOptions,
+}
+
+/**
+ * staged rules, either a map keyed by pattern (kept in config order) or a list of entries
+ */
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(untagged)]
+#[schemars(description = "commands keyed by glob pattern, or a list of rules")]
+pub enum StagedRules {
+  Map(IndexMap<String, StagedRule>),
+  List(Vec<StagedRuleEntry>),
+}
+
+impl Default for StagedRules {
+  fn default() -> Self {
+    StagedRules::Map(IndexMap::new())
+  }
+}
+
+impl StagedRules {
+  pub fn is_empty(&self) -> bool {
+    match self {
+      StagedRules::Map(rules) => rules.is_empty(),
+      StagedRules::List(rules) => rules.is_empty(),
+    }
+  }
+
+  /**
+   * rules in execution order
+   */
+  pub fn ordered(&self) -> Vec<StagedRuleEntry> {
+    let mut entries = match self {
+      StagedRules::Map(rules) => rules
+        .iter()
+        .map(|(pattern, rule)| StagedRuleEntry {
+          pattern: GlobPatterns::Single(pattern.to_string()),
+          options: rule.into(),
+        })
+        .collect::<Vec<StagedRuleEntry>>(),
+      StagedRules::List(rules) => rules.to_vec(),
+    };
+    // stable sort keeps the config order for the same priority
+    entries.sort_by_key(|entry| entry.options.priority.unwrap_or(0));
+    entries
+  }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+#[schemars(deny_unknown_fields)]
 pub struct StagedHooksConfig {
+  /// run the rules on the staged files before committing
   #[serde(default)]
   pub enabled: bool,
   #[serde(default)]
-  pub rules: HashMap<String, HookCommand>,
+  pub rules: StagedRules,
+  /// max rules running at the same time, defaults to the number of CPUs
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub concurrency: Option<usize>,
+  /// hide unstaged changes while running and restore them afterwards
+  #[serde(default = "default_true")]
+  pub stash: bool,
+  /// max length of a single command line, longer file lists are split into batches
+  #[serde(
+    default,
+    rename = "maxArgLength",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub max_arg_length: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLevel {
+  Error,
+  Warning,
+  Off,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/**
+ * a commit message rule, either a level, a value checked as an error or both
+ */
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(untagged)]
+pub enum LintRule<T> {
+  Level(RuleLevel),
+  Value(T),
+  Options {
+    level: RuleLevel,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<T>,
+  },
+}
+
+impl<T: Clone> LintRule<T> {
+  /**
+   * level and value of the rule, `default` fills in a missing value, `None` when it is off
+   */
+  pub fn resolve(&self, default: T) -> Option<(RuleLevel, T)> {
+    let (level, value) = match self {
+      LintRule::Level(level) => (*level, default),
+      LintRule::Value(value) => (RuleLevel::Error, value.clone()),
+      LintRule::Options { level, value } => (*level, value.clone().unwrap_or(default)),
+    };
+    (level != RuleLevel::Off).then_some((level, value))
+  }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DescriptionCase {
+  /// starts with a lower-case letter
+  Lower,
+  /// starts with an upper-case letter
+  Sentence,
+  /// has no lower-case letters
+  Upper,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
+#[schemars(deny_unknown_fields)]
+pub struct CommitLintRules {
+  /// max length of the first line, 100 when the rule is only set to a level
+  #[serde(default, rename = "headerMaxLength", skip_serializing_if = "Option::is_none")]
+  pub header_max_length: Option<LintRule<usize>>,
+  /// case of the description, `lower` when the rule is only set to a level
+  #[serde(default, rename = "descriptionCase", skip_serializing_if = "Option::is_none")]
+  pub description_case: Option<LintRule<DescriptionCase>>,
+  /// the description must not end with a period
+  #[serde(default, rename = "noTrailingPeriod", skip_serializing_if = "Option::is_none")]
+  pub no_trailing_period: Option<RuleLevel>,
+  /// allowed scopes
+  #[serde(default, rename = "validScopes", skip_serializing_if = "Option::is_none")]
+  pub valid_scopes: Option<LintRule<Vec<String>>>,
+  /// every commit must have a scope
+  #[serde(default, rename = "scopeRequired", skip_serializing_if = "Option::is_none")]
+  pub scope_required: Option<RuleLevel>,
+  /// a blank line must separate the body from the header
+  #[serde(default, rename = "bodyLeadingBlank", skip_serializing_if = "Option::is_none")]
+  pub body_leading_blank: Option<RuleLevel>,
+  /// a blank line must separate the footers from the lines above
+  #[serde(default, rename = "footerLeadingBlank", skip_serializing_if = "Option::is_none")]
+  pub footer_leading_blank: Option<RuleLevel>,
+  /// max length of every line of the body and footers, 100 when the rule is only set to a level
+  #[serde(default, rename = "maxLineLength", skip_serializing_if = "Option::is_none")]
+  pub max_line_length: Option<LintRule<usize>>,
+  /// words not allowed anywhere in the message, matched as whole words ignoring case
+  #[serde(default, rename = "forbiddenWords", skip_serializing_if = "Option::is_none")]
+  pub forbidden_words: Option<LintRule<Vec<String>>>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+#[schemars(deny_unknown_fields)]
 pub struct CommitLintConfig {
+  /// check the commit message follows conventional commits
   #[serde(default)]
   pub enabled: bool,
+  /// allowed commit types, defaults to the conventional commit types
   #[serde(
     default,
     rename = "validTypes",
     skip_serializing_if = "Option::is_none"
   )]
   pub valid_types: Option<Vec<String>>,
+  /// prepend the emoji of the commit type to the message
   #[serde(default, rename = "prependEmoji")]
   pub prepend_emoji: bool,
+  /// rules checked on top of the commit type, each one set to a level, a value or both
+  #[serde(default)]
+  pub rules: CommitLintRules,
 }
 
 fn default_base_url() -> Option<String> {
@@ -54,24 +325,30 @@ fn default_respond_in() -> Option<String> {
   Some("English".to_string())
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+#[schemars(deny_unknown_fields)]
 pub struct AIConfig {
+  /// generate commit messages with `igit commit`
   #[serde(default)]
   pub enabled: bool,
+  /// base url of an OpenAI compatible API
   #[serde(
     default = "default_base_url",
     rename = "baseUrl",
     skip_serializing_if = "Option::is_none"
   )]
   pub base_url: Option<String>,
+  /// key of the API
   #[serde(default, rename = "apiKey", skip_serializing_if = "Option::is_none")]
   pub api_key: Option<String>,
+  /// model generating the commit messages
   #[serde(
     default = "default_model",
     rename = "model",
     skip_serializing_if = "Option::is_none"
   )]
   pub model: Option<String>,
+  /// language of the generated commit messages
   #[serde(
     default = "default_respond_in",
     rename = "respondIn",
@@ -80,8 +357,19 @@ pub struct AIConfig {
   pub respond_in: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(untagged)]
+pub enum ConfigExtends {
+  Single(String),
+  Multiple(Vec<String>),
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+#[schemars(deny_unknown_fields)]
 pub struct IgitConfig {
+  /// configs merged under this one, local paths or packages in node_modules
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub extends: Option<ConfigExtends>,
   #[serde(default)]
   pub hooks: HooksConfig,
   #[serde(default)]
@@ -92,67 +380,261 @@ pub struct IgitConfig {
   pub ai: AIConfig,
 }
 
-pub fn init() -> std::io::Result<()> {
-  let config_file = Path::new(".config/igit.yaml");
-  if config_file.exists() {
-    return Ok(());
-  }
-  let config_dir = Path::new(".config");
-  if !config_dir.exists() {
-    fs::create_dir_all(config_dir)?;
-  }
-
-  fs::write(
-    config_file,
-    "# yaml-language-server: $schema=https://igit.erguotou.me/schema/0.0.2/schema.json
-hooks:
-  enabled: true
-  hooks: {}
-staged_hooks:
-  enabled: true
-  rules:
-    '**/*.{css,scss,less,styl,stylus}': stylelint --fix
-    '**/*.{js,jsx,ts,tsx}': biome check --write
-commit_msg:
-  enabled: true
-  prependEmoji: true
-ai:
-  enabled: true
-  baseUrl: https://api.deepseek.com
-  # apiKey:
-  model: deepseek-chat
-  respondIn: English
-",
-  )
+/// config files of a directory in search order, manifests only count when they have an igit config
+static CONFIG_FILES: &[&str] = &[
+  ".config/igit.yaml",
+  "igit.yaml",
+  ".config/igit.toml",
+  "igit.toml",
+  ".config/igit.json",
+  "igit.json",
+  "package.json",
+  "Cargo.toml",
+];
+
+/**
+ * config file placed directly in `dir`
+ */
+pub fn config_file_in(dir: &Path) -> Option<PathBuf> {
+  CONFIG_FILES.iter().map(|name| dir.join(name)).find(|path| format::has_config(path))
 }
 
-pub fn parse() -> Result<IgitConfig, String> {
-  let mut path: String = ".config/igit.yaml".to_string();
-  if !Path::new(&path).exists() {
-    path = "igit.yaml".to_string();
-    if !Path::new(&path).exists() {
-      let mut home_dir = home_dir().unwrap();
-      home_dir.push(".config");
-      home_dir.push("igit.yaml");
-      if !Path::new(&home_dir).exists() {
-        return Err(
-          "Failed to find config file, please use `igit init` to create one.".to_string(),
-        );
+/**
+ * nearest config file from `start` up to the repository root
+ */
+pub fn find_repo_config_file_from(start: &Path) -> Option<PathBuf> {
+  let start = fs::canonicalize(start).unwrap_or(start.to_path_buf());
+  let root = git::root().ok().map(|root| fs::canonicalize(&root).unwrap_or(root));
+  for dir in start.ancestors() {
+    if let Some(path) = config_file_in(dir) {
+      return Some(path);
+    }
+    // outside of a repository only the start directory is searched
+    if root.as_deref().is_none_or(|root| dir == root) {
+      break;
+    }
+  }
+  None
+}
+
+/**
+ * personal config at `~/.config/igit.yaml`, `~/.config/igit.toml` or `~/.config/igit.json`
+ */
+pub fn home_config_file() -> Option<PathBuf> {
+  let config_dir = home_dir()?.join(".config");
+  ["igit.yaml", "igit.toml", "igit.json"]
+    .iter()
+    .map(|name| config_dir.join(name))
+    .find(|path| path.is_file())
+}
+
+/**
+ * nearest config file from `start` up to the repository root,
+ * falls back to `~/.config/igit.yaml`
+ */
+pub fn find_config_file_from(start: &Path) -> Option<PathBuf> {
+  find_repo_config_file_from(start).or_else(home_config_file)
+}
+
+/**
+ * config from the merged layers, the types of the config files are only checked here
+ */
+pub fn from_value(value: Value) -> Result<IgitConfig, ConfigIssue> {
+  serde_path_to_error::deserialize(value).map_err(|e| {
+    let issue = diagnostic::yaml_error("merged config", e.inner());
+    match e.path().to_string() {
+      key if key == "." => issue,
+      key => issue.with_key(&key),
+    }
+  })
+}
+
+/**
+ * config from the merged `files`, errors point to the file setting the offending key
+ */
+fn from_files(value: Value, files: &[PathBuf]) -> Result<IgitConfig, ConfigIssue> {
+  from_value(value).map_err(|issue| diagnostic::locate(issue, files))
+}
+
+/**
+ * print the warnings and turn the error into a message
+ */
+fn report<T>(result: Result<T, ConfigIssue>, warnings: &[ConfigIssue]) -> Result<T, String> {
+  diagnostic::warn(warnings);
+  result.map_err(|e| format!("Invalid config {}", e))
+}
+
+pub fn parse_file(path: &Path) -> Result<IgitConfig, String> {
+  let mut warnings = vec![];
+  let mut files = vec![];
+  let result = extends::load(path, &mut warnings, &mut files).and_then(|value| from_files(value, &files));
+  report(result, &warnings)
+}
+
+#[derive(Clone, Copy)]
+enum EnvKind {
+  String,
+  Bool,
+  Number,
+}
+
+/// environment variables overriding the config files
+static ENV_OVERRIDES: &[(&str, &[&str], EnvKind)] = &[
+  ("IGIT_HOOKS_ENABLED", &["hooks", "enabled"], EnvKind::Bool),
+  ("IGIT_HOOKS_LAUNCHER", &["hooks", "launcher"], EnvKind::String),
+  ("IGIT_STAGED_HOOKS_ENABLED", &["staged_hooks", "enabled"], EnvKind::Bool),
+  ("IGIT_STAGED_HOOKS_CONCURRENCY", &["staged_hooks", "concurrency"], EnvKind::Number),
+  ("IGIT_STAGED_HOOKS_STASH", &["staged_hooks", "stash"], EnvKind::Bool),
+  ("IGIT_COMMIT_MSG_ENABLED", &["commit_msg", "enabled"], EnvKind::Bool),
+  ("IGIT_AI_ENABLED", &["ai", "enabled"], EnvKind::Bool),
+  ("IGIT_AI_BASE_URL", &["ai", "baseUrl"], EnvKind::String),
+  ("IGIT_AI_API_KEY", &["ai", "apiKey"], EnvKind::String),
+  ("IGIT_AI_MODEL", &["ai", "model"], EnvKind::String),
+  ("IGIT_AI_RESPOND_IN", &["ai", "respondIn"], EnvKind::String),
+];
+
+fn env_value(name: &str, value: &str, kind: EnvKind) -> Result<Value, String> {
+  let invalid = |expected: &str| format!("Invalid value `{}` for {}, expected {}", value, name, expected);
+  match kind {
+    EnvKind::String => Ok(Value::String(value.to_string())),
+    EnvKind::Bool => match value.to_lowercase().as_str() {
+      "true" | "1" | "yes" | "on" => Ok(Value::Bool(true)),
+      "false" | "0" | "no" | "off" => Ok(Value::Bool(false)),
+      _ => Err(invalid("a boolean")),
+    },
+    EnvKind::Number => value
+      .parse::<u64>()
+      .map(|n| Value::Number(n.into()))
+      .map_err(|_| invalid("a number")),
+  }
+}
+
+/**
+ * config layer built from `IGIT_*` environment variables
+ */
+fn env_layer<F>(get_var: F) -> Result<Value, String>
+where
+  F: Fn(&str) -> Option<String>,
+{
+  let mut layer = Mapping::new();
+  for (name, path, kind) in ENV_OVERRIDES {
+    let Some(value) = get_var(name) else {
+      continue;
+    };
+    let value = env_value(name, &value, *kind)?;
+    let section = layer
+      .entry(Value::String(path[0].to_string()))
+      .or_insert_with(|| Value::Mapping(Mapping::new()));
+    if let Value::Mapping(section) = section {
+      section.insert(Value::String(path[1].to_string()), value);
+    }
+  }
+  Ok(Value::Mapping(layer))
+}
+
+/**
+ * merge the layers of the config: the repository config overrides the home config,
+ * except for the fields of the personal `ai` section the home config sets, and the
+ * environment overrides both
+ */
+fn merge_layers(repo: Option<Value>, home: Option<Value>, env: Value) -> Value {
+  let merged = match (repo, home) {
+    (Some(repo), Some(home)) => {
+      let home_ai = home.get("ai").cloned();
+      let mut merged = extends::merge(home, repo);
+      // an empty `ai:` in the home config leaves the repository fields alone
+      if let (Some(Value::Mapping(home_ai)), Value::Mapping(mapping)) = (home_ai, &mut merged) {
+        let key = Value::String("ai".to_string());
+        let mut ai = match mapping.get(&key) {
+          Some(Value::Mapping(repo_ai)) => repo_ai.clone(),
+          _ => Mapping::new(),
+        };
+        for (field, value) in home_ai.into_iter().filter(|(_, value)| !value.is_null()) {
+          ai.insert(field, value);
+        }
+        mapping.insert(key, Value::Mapping(ai));
       }
-      path = home_dir.to_str().unwrap().to_string();
+      merged
     }
+    (Some(layer), None) | (None, Some(layer)) => layer,
+    (None, None) => Value::Mapping(Mapping::new()),
+  };
+  extends::merge(merged, env)
+}
+
+/**
+ * repository and home config files for `start`, at least one of them exists
+ */
+fn layer_files(start: &Path) -> Result<(Option<PathBuf>, Option<PathBuf>), String> {
+  let repo_file = find_repo_config_file_from(start);
+  let home_file = home_config_file();
+  if repo_file.is_none() && home_file.is_none() {
+    return Err("Failed to find config file, please use `igit init` to create one.".to_string());
   }
-  let ret = fs::read_to_string(path);
-  if ret.is_err() {
-    return Err("Failed to read config file".to_string());
+  Ok((repo_file, home_file))
+}
+
+fn load_from(repo_file: Option<PathBuf>, home_file: Option<PathBuf>, warnings: &mut Vec<ConfigIssue>) -> Result<IgitConfig, ConfigIssue> {
+  let mut files = vec![];
+  // the home config is read first as the repository config overrides it
+  let home = home_file.map(|path| extends::load(&path, warnings, &mut files)).transpose()?;
+  let repo = repo_file.map(|path| extends::load(&path, warnings, &mut files)).transpose()?;
+  let env = env_layer(|name| std::env::var(name).ok()).map_err(|e| ConfigIssue::error("environment", e))?;
+  from_files(merge_layers(repo, home, env), &files)
+}
+
+/**
+ * layered config for the nearest config file from `start`
+ */
+pub fn parse_from(start: &Path) -> Result<IgitConfig, String> {
+  let (repo_file, home_file) = layer_files(start)?;
+  let mut warnings = vec![];
+  let result = load_from(repo_file, home_file, &mut warnings);
+  report(result, &warnings)
+}
+
+pub fn parse() -> Result<IgitConfig, String> {
+  let current_dir = std::env::current_dir().map_err(|e| format!("Failed to get current directory: {}", e))?;
+  parse_from(&current_dir)
+}
+
+static MASKED_SECRET: &str = "********";
+
+/**
+ * the config in effect with everything it extends merged in, as yaml.
+ * `ai.apiKey` is masked so the output can be shared
+ */
+pub fn resolved() -> Result<String, String> {
+  to_resolved_yaml(parse()?)
+}
+
+fn to_resolved_yaml(mut config: IgitConfig) -> Result<String, String> {
+  config.ai.api_key = config.ai.api_key.map(|_| MASKED_SECRET.to_string());
+  serde_yaml::to_string(&config).map_err(|e| format!("Failed to serialize config: {}", e))
+}
+
+/**
+ * errors and warnings of the config files in effect, an empty list when they are fine
+ */
+pub fn validate() -> Result<Vec<ConfigIssue>, String> {
+  let current_dir = std::env::current_dir().map_err(|e| format!("Failed to get current directory: {}", e))?;
+  let (repo_file, home_file) = layer_files(&current_dir)?;
+  let mut issues = vec![];
+  if let Err(error) = load_from(repo_file, home_file, &mut issues) {
+    issues.push(error);
   }
-  let file_content = ret.unwrap();
-  let config: IgitConfig = serde_yaml::from_str(&file_content)
-    .expect("Failed to parse config, please check your config file.");
-  Ok(config)
+  Ok(issues)
 }
 
 pub fn check() -> Result<IgitConfig, String> {
+  check_git()?;
+  parse()
+}
+
+/**
+ * fails when git is missing or the current directory is not in a repository
+ */
+pub fn check_git() -> Result<(), String> {
   let git_exists = git::is_git_installed();
 
   if !git_exists {
@@ -163,8 +645,103 @@ pub fn check() -> Result<IgitConfig, String> {
 
   if !is_git_repo {
     let current_dir = std::env::current_dir().unwrap();
-    return Err(format!("Current directory '{}' is not a git repository", current_dir.display()).into());
+    return Err(format!("Current directory '{}' is not a git repository", current_dir.display()));
+  }
+  Ok(())
+}
+
+/* ------------ test ------------ */
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn yaml(content: &str) -> Value {
+    serde_yaml::from_str(content).unwrap()
+  }
+
+  #[test]
+  fn test_config_file_in() {
+    let dir = std::env::temp_dir().join("igit_config_file_in");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("package.json"), r#"{ "name": "app" }"#).unwrap();
+    assert_eq!(config_file_in(&dir), None);
+    fs::write(dir.join("package.json"), r#"{ "name": "app", "igit": {} }"#).unwrap();
+    assert_eq!(config_file_in(&dir), Some(dir.join("package.json")));
+    fs::write(dir.join("igit.toml"), "[hooks]\nenabled = true\n").unwrap();
+    assert_eq!(config_file_in(&dir), Some(dir.join("igit.toml")));
+    fs::write(dir.join("igit.yaml"), "hooks:\n  enabled: true\n").unwrap();
+    assert_eq!(config_file_in(&dir), Some(dir.join("igit.yaml")));
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_parse_file_removes_null_keys_of_parents() {
+    let dir = std::env::temp_dir().join("igit_parse_file_null");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+      dir.join("base.yaml"),
+      "hooks:\n  enabled: true\n  hooks:\n    post-merge: npm install\n    pre-push: npm test\n",
+    )
+    .unwrap();
+    fs::write(dir.join("igit.yaml"), "extends: ./base.yaml\nhooks:\n  hooks:\n    post-merge: null\n").unwrap();
+    let config = parse_file(&dir.join("igit.yaml")).unwrap();
+    assert!(config.hooks.enabled);
+    assert_eq!(config.hooks.hooks.keys().collect::<Vec<&String>>(), vec!["pre-push"]);
+
+    // type errors of the merged config point to the file setting the key
+    fs::write(dir.join("igit.yaml"), "extends: ./base.yaml\nhooks:\n  enabled: maybe\n").unwrap();
+    let error = parse_file(&dir.join("igit.yaml")).unwrap_err();
+    assert!(error.starts_with(&format!("Invalid config {}:3:12: `hooks.enabled`", dir.join("igit.yaml").display())));
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_env_layer() {
+    let vars = HashMap::from([
+      ("IGIT_AI_API_KEY", "123"),
+      ("IGIT_STAGED_HOOKS_CONCURRENCY", "2"),
+      ("IGIT_HOOKS_ENABLED", "off"),
+    ]);
+    let layer = env_layer(|name| vars.get(name).map(|v| v.to_string())).unwrap();
+    assert_eq!(
+      layer,
+      yaml("hooks:\n  enabled: false\nstaged_hooks:\n  concurrency: 2\nai:\n  apiKey: '123'\n")
+    );
+    let error = env_layer(|name| (name == "IGIT_AI_ENABLED").then(|| "maybe".to_string())).unwrap_err();
+    assert_eq!(error, "Invalid value `maybe` for IGIT_AI_ENABLED, expected a boolean");
+  }
+
+  #[test]
+  fn test_merge_layers() {
+    let repo = yaml("hooks:\n  enabled: true\nai:\n  enabled: true\n  model: deepseek-chat\n");
+    let home = yaml("hooks:\n  enabled: false\nai:\n  apiKey: secret\n  model: gpt-4o\n");
+    let env = yaml("ai:\n  model: gpt-4o-mini\n");
+    let config = from_value(merge_layers(Some(repo.clone()), Some(home), env)).unwrap();
+    assert!(config.hooks.enabled);
+    assert!(config.ai.enabled);
+    assert_eq!(config.ai.api_key.as_deref(), Some("secret"));
+    assert_eq!(config.ai.model.as_deref(), Some("gpt-4o-mini"));
+
+    let config = from_value(merge_layers(Some(repo.clone()), None, Value::Mapping(Mapping::new()))).unwrap();
+    assert_eq!(config.ai.model.as_deref(), Some("deepseek-chat"));
+
+    // home fields are merged one by one, an empty section or a null field keeps the repository value
+    for home in ["ai:\n", "ai:\n  model: null\n  respondIn: en\n"] {
+      let config = from_value(merge_layers(Some(repo.clone()), Some(yaml(home)), Value::Mapping(Mapping::new()))).unwrap();
+      assert!(config.ai.enabled);
+      assert_eq!(config.ai.model.as_deref(), Some("deepseek-chat"));
+    }
+  }
+
+  #[test]
+  fn test_resolved_masks_api_key() {
+    let config = from_value(yaml("ai:\n  enabled: true\n  apiKey: sk-secret\n")).unwrap();
+    let output = to_resolved_yaml(config).unwrap();
+    assert!(!output.contains("sk-secret"));
+    assert!(output.contains("apiKey: '********'"));
+    let output = to_resolved_yaml(from_value(yaml("ai:\n  enabled: true\n")).unwrap()).unwrap();
+    assert!(!output.contains("apiKey"));
   }
-  let config = parse()?;
-  Ok(config)
 }
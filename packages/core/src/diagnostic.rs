@@ -0,0 +1,431 @@
+use crate::config::IgitConfig;
+use crate::format::{self, Format};
+use crate::log::LOG_PREFIX;
+use napi_derive::napi;
+use serde::Deserializer;
+use serde_yaml::Value;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+static TOP_LEVEL_KEYS: &[&str] = &["extends", "hooks", "staged_hooks", "commit_msg", "ai"];
+
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+  /// config file the issue was found in
+  pub file: String,
+  /// one-based line of the issue when known
+  pub line: Option<u32>,
+  /// one-based column of the issue when known
+  pub column: Option<u32>,
+  /// dotted path of the offending key, like `staged_hooks.concurrency`
+  pub key: Option<String>,
+  pub message: String,
+  /// `error` or `warning`
+  pub severity: String,
+}
+
+impl ConfigIssue {
+  pub fn error(file: &str, message: String) -> Self {
+    ConfigIssue {
+      file: file.to_string(),
+      line: None,
+      column: None,
+      key: None,
+      message,
+      severity: "error".to_string(),
+    }
+  }
+
+  fn warning(file: &str, key: String, message: String) -> Self {
+    ConfigIssue {
+      key: Some(key),
+      severity: "warning".to_string(),
+      ..ConfigIssue::error(file, message)
+    }
+  }
+
+  pub fn at(mut self, line: u32, column: u32) -> Self {
+    self.line = Some(line);
+    self.column = Some(column);
+    self
+  }
+
+  pub fn with_key(mut self, key: &str) -> Self {
+    self.key = Some(key.to_string());
+    self
+  }
+
+  pub fn is_error(&self) -> bool {
+    self.severity == "error"
+  }
+}
+
+impl fmt::Display for ConfigIssue {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.file)?;
+    if let Some(line) = self.line {
+      write!(f, ":{}", line)?;
+      if let Some(column) = self.column {
+        write!(f, ":{}", column)?;
+      }
+    }
+    write!(f, ": ")?;
+    if let Some(key) = &self.key {
+      write!(f, "`{}` ", key)?;
+    }
+    write!(f, "{}", self.message)
+  }
+}
+
+/**
+ * error message without the location, it is reported separately
+ */
+pub fn without_location(message: String) -> String {
+  match message.find(" at line ") {
+    Some(index) => message[..index].to_string(),
+    None => message,
+  }
+}
+
+/**
+ * error of serde_yaml with its location
+ */
+pub fn yaml_error(file: &str, error: &serde_yaml::Error) -> ConfigIssue {
+  let message = without_location(error.to_string());
+  // errors raised while deserializing repeat the key in front of the message
+  let message = match message.split_once(": ") {
+    Some((key, rest)) if !key.contains(' ') => rest.to_string(),
+    _ => message,
+  };
+  let mut issue = ConfigIssue::error(file, message);
+  if let Some(location) = error.location() {
+    issue.line = Some(location.line() as u32);
+    issue.column = Some(location.column() as u32);
+  }
+  issue
+}
+
+/**
+ * keys of a toml table header like `[package.metadata.igit]`
+ */
+fn table_header(line: &str) -> Option<Vec<&str>> {
+  let header = line.trim().trim_start_matches('[').trim_end_matches(']');
+  line.trim_start().starts_with('[').then(|| header.split('.').map(|key| key.trim().trim_matches(['"', '\''])).collect())
+}
+
+/**
+ * best effort line of a key path in yaml, json or toml, each key is searched
+ * below the line of its parent
+ */
+fn key_line(content: &str, key: &str) -> Option<u32> {
+  let lines = content.lines().collect::<Vec<&str>>();
+  let mut from = 0;
+  let mut found = None;
+  // keys of the last matched table header that are still to be consumed
+  let mut header: Vec<&str> = vec![];
+  for segment in key.split('.') {
+    if let Some(position) = header.iter().position(|key| *key == segment) {
+      header.drain(..=position);
+      continue;
+    }
+    let quoted = [segment.to_string(), format!("\"{}\"", segment), format!("'{}'", segment)];
+    let index = (from..lines.len()).find(|&i| {
+      if let Some(keys) = table_header(lines[i]) {
+        return keys.contains(&segment);
+      }
+      let line = lines[i].trim_start().trim_start_matches("- ");
+      quoted.iter().any(|key| {
+        line
+          .strip_prefix(key.as_str())
+          .is_some_and(|rest| rest.starts_with(':') || rest.trim_start().starts_with('='))
+      })
+    })?;
+    header = table_header(lines[index]).unwrap_or_default();
+    if let Some(position) = header.iter().position(|key| *key == segment) {
+      header.drain(..=position);
+    }
+    found = Some(index as u32 + 1);
+    from = index + 1;
+  }
+  found
+}
+
+fn normalize(key: &str) -> String {
+  key.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase()
+}
+
+fn camel_case(key: &str) -> String {
+  let mut camel = String::new();
+  let mut upper = false;
+  for c in key.chars() {
+    if c == '_' || c == '-' {
+      upper = !camel.is_empty();
+    } else if upper {
+      camel.extend(c.to_uppercase());
+      upper = false;
+    } else {
+      camel.push(c);
+    }
+  }
+  camel
+}
+
+/**
+ * the key probably meant by an unknown key: sections are snake_case
+ * and the keys inside them are camelCase
+ */
+pub fn suggestion(key: &str) -> Option<String> {
+  match key.rsplit_once('.') {
+    None => TOP_LEVEL_KEYS
+      .iter()
+      .find(|known| normalize(known) == normalize(key))
+      .map(|known| known.to_string()),
+    Some((parent, name)) => {
+      let camel = camel_case(name);
+      (camel != name).then(|| format!("{}.{}", parent, camel))
+    }
+  }
+}
+
+fn unknown_key(file: &str, content: &str, prefix: &[&str], key: String) -> ConfigIssue {
+  let message = match suggestion(&key) {
+    Some(known) => format!("is not a known key and is ignored, did you mean `{}`?", known),
+    None => "is not a known key and is ignored".to_string(),
+  };
+  let line = key_line(content, &full_key(prefix, &key));
+  ConfigIssue {
+    line,
+    column: line.map(|_| 1),
+    ..ConfigIssue::warning(file, key, message)
+  }
+}
+
+fn full_key(prefix: &[&str], key: &str) -> String {
+  prefix.iter().copied().chain([key]).collect::<Vec<&str>>().join(".")
+}
+
+/**
+ * deserialize the config collecting the paths of unknown keys
+ */
+fn typed_check<'de, D>(deserializer: D, unknown: &mut Vec<String>) -> Result<(), serde_path_to_error::Error<D::Error>>
+where
+  D: Deserializer<'de>,
+{
+  let mut collect = |path: serde_ignored::Path| unknown.push(path.to_string());
+  let deserializer = serde_ignored::Deserializer::new(deserializer, &mut collect);
+  serde_path_to_error::deserialize(deserializer).map(|_: IgitConfig| ())
+}
+
+/**
+ * copy of the value without `key: null` entries, they only remove keys of the
+ * configs a file extends
+ */
+fn without_nulls(value: &Value) -> Value {
+  match value {
+    Value::Mapping(mapping) => Value::Mapping(
+      mapping
+        .iter()
+        .filter(|(_, value)| !value.is_null())
+        .map(|(key, value)| (key.clone(), without_nulls(value)))
+        .collect(),
+    ),
+    Value::Sequence(items) => Value::Sequence(items.iter().map(without_nulls).collect()),
+    value => value.clone(),
+  }
+}
+
+/**
+ * parse the content of a config file, syntax errors point to their line and
+ * column, unknown keys are returned as warnings. types are only checked once
+ * the file is merged with the configs it extends, see `locate`
+ */
+pub fn check(path: &Path, content: &str) -> Result<(Value, Vec<ConfigIssue>), ConfigIssue> {
+  let file = path.display().to_string();
+  let Some((value, prefix)) = format::parse(path, content)? else {
+    let keys = format::embedded_keys(path).iter().map(|key| key.join(".")).collect::<Vec<String>>();
+    return Err(ConfigIssue::error(&file, format!("No igit config found under `{}`", keys.join("` or `"))));
+  };
+  if value.as_mapping().is_some_and(|mapping| mapping.is_empty()) {
+    return Ok((value, vec![]));
+  }
+
+  let mut unknown = vec![];
+  // a file may not be a valid config on its own, unknown keys found before an error are kept
+  let _ = typed_check(without_nulls(&value), &mut unknown);
+  // json configs may point editors to the schema
+  let warnings = unknown
+    .into_iter()
+    .filter(|key| key != "$schema")
+    .map(|key| unknown_key(&file, content, prefix, key))
+    .collect();
+  Ok((value, warnings))
+}
+
+/**
+ * error of a config file read on its own, with the exact location in yaml files
+ */
+fn file_error(path: &Path, content: &str) -> Option<ConfigIssue> {
+  let file = path.display().to_string();
+  let (value, prefix) = format::parse(path, content).ok()??;
+  let mut unknown = vec![];
+  // yaml files are read again to know the exact location of errors,
+  // the other formats locate errors by their key
+  let result = if Format::of(path) == Format::Yaml && prefix.is_empty() {
+    typed_check(serde_yaml::Deserializer::from_str(content), &mut unknown)
+  } else {
+    typed_check(value, &mut unknown)
+  };
+  let error = result.err()?;
+  let key = error.path().to_string();
+  let mut issue = yaml_error(&file, error.inner());
+  if key != "." {
+    issue.line = issue.line.or_else(|| key_line(content, &full_key(prefix, &key)));
+    issue.key = Some(key);
+  }
+  Some(issue)
+}
+
+/**
+ * point an error of the merged config to the file setting the offending key,
+ * `files` go from the least to the most important one
+ */
+pub fn locate(issue: ConfigIssue, files: &[PathBuf]) -> ConfigIssue {
+  let Some(key) = issue.key.clone() else {
+    return issue;
+  };
+  for path in files.iter().rev() {
+    let Ok(content) = fs::read_to_string(path) else {
+      continue;
+    };
+    if let Some(error) = file_error(path, &content).filter(|error| error.key.as_deref() == Some(key.as_str())) {
+      return error;
+    }
+    let Ok(Some((_, prefix))) = format::parse(path, &content) else {
+      continue;
+    };
+    if let Some(line) = key_line(&content, &full_key(prefix, &key)) {
+      return ConfigIssue {
+        file: path.display().to_string(),
+        line: Some(line),
+        column: Some(1),
+        ..issue
+      };
+    }
+  }
+  issue
+}
+
+/**
+ * print warnings, each one only once per process as the config is parsed by every command
+ */
+pub fn warn(issues: &[ConfigIssue]) {
+  static PRINTED: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+  let mut printed = PRINTED.get_or_init(Default::default).lock().unwrap();
+  for issue in issues {
+    let message = issue.to_string();
+    if printed.insert(message.clone()) {
+      println!("{}\x1b[33mWarning: {}\x1b[0m", LOG_PREFIX, message);
+    }
+  }
+}
+
+/* ------------ test ------------ */
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn check_str(content: &str) -> Result<(Value, Vec<ConfigIssue>), ConfigIssue> {
+    check(Path::new("igit.yaml"), content)
+  }
+
+  #[test]
+  fn test_check_errors() {
+    let content = "hooks:\n  enabled: true\nstaged_hooks:\n  concurrency: many\n";
+    // types are checked on the merged config
+    assert!(check_str(content).is_ok());
+    let error = file_error(Path::new("igit.yaml"), content).unwrap();
+    assert_eq!(error.key.as_deref(), Some("staged_hooks.concurrency"));
+    assert_eq!((error.line, error.column), (Some(4), Some(16)));
+    assert_eq!(
+      error.to_string(),
+      "igit.yaml:4:16: `staged_hooks.concurrency` invalid type: string \"many\", expected usize"
+    );
+
+    let error = check_str("hooks:\n  enabled: [true\n").unwrap_err();
+    assert_eq!(error.key, None);
+    assert_eq!(error.line, Some(3));
+    assert!(error.is_error());
+
+    assert_eq!(check_str("# nothing yet\n").unwrap().1, vec![]);
+  }
+
+  #[test]
+  fn test_check_unknown_keys() {
+    let (_, warnings) = check_str(
+      "stagedHooks:\n  enabled: true\ncommit_msg:\n  enabled: true\n  prepend_emoji: true\n  foo: 1\n",
+    )
+    .unwrap();
+    let messages = warnings.iter().map(|w| w.to_string()).collect::<Vec<String>>();
+    assert_eq!(
+      messages,
+      vec![
+        "igit.yaml:1:1: `stagedHooks` is not a known key and is ignored, did you mean `staged_hooks`?",
+        "igit.yaml:5:1: `commit_msg.prepend_emoji` is not a known key and is ignored, did you mean `commit_msg.prependEmoji`?",
+        "igit.yaml:6:1: `commit_msg.foo` is not a known key and is ignored",
+      ]
+    );
+    assert!(warnings.iter().all(|w| !w.is_error()));
+  }
+
+  #[test]
+  fn test_check_other_formats() {
+    let (_, warnings) = check(Path::new("igit.toml"), "[hooks]\nenabled = true\n\n[commit_msg]\nprepend_emoji = true\n").unwrap();
+    assert_eq!(
+      warnings[0].to_string(),
+      "igit.toml:5:1: `commit_msg.prepend_emoji` is not a known key and is ignored, did you mean `commit_msg.prependEmoji`?"
+    );
+
+    let cargo = "[package]\nname = 'app'\n\n[package.metadata.igit.staged_hooks]\nenabled = true\nconcurrency = 'many'\n";
+    assert!(check(Path::new("Cargo.toml"), cargo).is_ok());
+    let error = file_error(Path::new("Cargo.toml"), cargo).unwrap();
+    assert_eq!(error.key.as_deref(), Some("staged_hooks.concurrency"));
+    assert_eq!(error.line, Some(6));
+
+    let error = check(Path::new("package.json"), "{\n  \"name\": \"app\"\n}\n").unwrap_err();
+    assert_eq!(error.message, "No igit config found under `igit`");
+  }
+
+  #[test]
+  fn test_check_null_overrides() {
+    let (_, warnings) = check_str("hooks:\n  hooks:\n    post-merge: null\n    pre-psuh: ~\nai: null\n").unwrap();
+    assert!(warnings.is_empty());
+    let (_, warnings) = check_str("hooks:\n  hooks:\n    post-merge: null\n  foo: 1\n").unwrap();
+    assert_eq!(warnings[0].key.as_deref(), Some("hooks.foo"));
+  }
+
+  #[test]
+  fn test_locate() {
+    let dir = std::env::temp_dir().join("igit_locate");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let parent = dir.join("parent.yaml");
+    let child = dir.join("igit.yaml");
+    fs::write(&parent, "staged_hooks:\n  enabled: true\n  concurrency: many\n").unwrap();
+    fs::write(&child, "hooks:\n  hooks:\n    post-merge: null\n").unwrap();
+    let issue = ConfigIssue::error("merged config", "invalid type".to_string()).with_key("staged_hooks.concurrency");
+    let located = locate(issue.clone(), &[parent.clone(), child.clone()]);
+    assert_eq!(located.file, parent.display().to_string());
+    assert_eq!((located.line, located.column), (Some(3), Some(16)));
+
+    // the most important file setting the key wins
+    fs::write(&child, "staged_hooks:\n  concurrency: 2\nhooks:\n  hooks:\n    post-merge: null\n").unwrap();
+    let located = locate(issue.clone(), &[parent.clone(), child.clone()]);
+    assert_eq!((located.file, located.line), (child.display().to_string(), Some(2)));
+    assert_eq!(locate(issue.clone(), &[]), issue);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
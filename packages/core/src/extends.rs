@@ -0,0 +1,270 @@
+use crate::config;
+use crate::diagnostic::{self, ConfigIssue};
+use serde_yaml::{Mapping, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+static EXTENDS_KEY: &str = "extends";
+
+/**
+ * paths of the values of `extends` in a config, a single string or a list
+ */
+fn extends_of(value: &Value) -> Result<Vec<String>, String> {
+  match value.get(EXTENDS_KEY) {
+    None | Some(Value::Null) => Ok(vec![]),
+    Some(Value::String(parent)) => Ok(vec![parent.to_string()]),
+    Some(Value::Sequence(parents)) => parents
+      .iter()
+      .map(|parent| match parent {
+        Value::String(parent) => Ok(parent.to_string()),
+        _ => Err("`extends` only accepts strings".to_string()),
+      })
+      .collect(),
+    Some(_) => Err("`extends` must be a string or a list of strings".to_string()),
+  }
+}
+
+/**
+ * config file of a local file or directory
+ */
+fn config_file_at(path: &Path) -> Option<PathBuf> {
+  if path.is_file() {
+    return Some(path.to_path_buf());
+  }
+  if path.is_dir() {
+    return config::config_file_in(path);
+  }
+  None
+}
+
+/**
+ * resolve an `extends` entry, local paths are relative to the extending config and
+ * anything else is looked up in the `node_modules` directories above it
+ */
+pub fn resolve(parent: &str, config_dir: &Path) -> Result<PathBuf, String> {
+  let is_local = parent.starts_with('.') || Path::new(parent).is_absolute();
+  let found = if is_local {
+    config_file_at(&config_dir.join(parent))
+  } else {
+    config_dir
+      .ancestors()
+      .find_map(|dir| config_file_at(&dir.join("node_modules").join(parent)))
+  };
+  found.ok_or_else(|| {
+    format!(
+      "Can not find the config `{}` extended from {}",
+      parent,
+      config_dir.display()
+    )
+  })
+}
+
+/**
+ * merge `child` over `parent`:
+ * - mappings are merged key by key, a `null` value in the child removes the key
+ * - `staged_hooks.rules` in the map form replaces rules of the same pattern, lists are concatenated
+ * - any other value, including lists like `validTypes` and hook commands, is replaced
+ */
+pub fn merge(parent: Value, child: Value) -> Value {
+  merge_at(parent, child, &[])
+}
+
+fn merge_at(parent: Value, child: Value, path: &[&str]) -> Value {
+  match (parent, child) {
+    (Value::Mapping(parent), Value::Mapping(child)) if path == ["staged_hooks", "rules"] => {
+      Value::Mapping(merge_mapping(parent, child, |_, _, child| child))
+    }
+    (Value::Sequence(mut parent), Value::Sequence(child)) if path == ["staged_hooks", "rules"] => {
+      parent.extend(child);
+      Value::Sequence(parent)
+    }
+    (Value::Mapping(parent), Value::Mapping(child)) => {
+      Value::Mapping(merge_mapping(parent, child, |key, parent, child| {
+        let mut path = path.to_vec();
+        path.push(key);
+        merge_at(parent, child, &path)
+      }))
+    }
+    (_, child) => child,
+  }
+}
+
+fn merge_mapping<F>(mut parent: Mapping, child: Mapping, merge_value: F) -> Mapping
+where
+  F: Fn(&str, Value, Value) -> Value,
+{
+  // keys keep their position so rules run in the order of the parent
+  for (key, value) in child {
+    if value.is_null() {
+      parent.shift_remove(&key);
+      continue;
+    }
+    match parent.get_mut(&key) {
+      Some(existing) => {
+        let parent_value = std::mem::take(existing);
+        *existing = merge_value(key.as_str().unwrap_or_default(), parent_value, value);
+      }
+      None => {
+        parent.insert(key, value);
+      }
+    }
+  }
+  parent
+}
+
+fn read(path: &Path, warnings: &mut Vec<ConfigIssue>) -> Result<Value, ConfigIssue> {
+  let file = path.display().to_string();
+  let content = fs::read_to_string(path)
+    .map_err(|e| ConfigIssue::error(&file, format!("Failed to read config file: {}", e)))?;
+  let (value, issues) = diagnostic::check(path, &content)?;
+  warnings.extend(issues);
+  Ok(value)
+}
+
+fn load_with(
+  path: &Path,
+  stack: &mut Vec<PathBuf>,
+  warnings: &mut Vec<ConfigIssue>,
+  files: &mut Vec<PathBuf>,
+) -> Result<Value, ConfigIssue> {
+  let file = path.display().to_string();
+  let extends_error = |message: String| ConfigIssue::error(&file, message).with_key(EXTENDS_KEY);
+  let canonical = fs::canonicalize(path).unwrap_or(path.to_path_buf());
+  if stack.contains(&canonical) {
+    let mut cycle = stack
+      .iter()
+      .skip_while(|p| **p != canonical)
+      .map(|p| p.display().to_string())
+      .collect::<Vec<String>>();
+    cycle.push(canonical.display().to_string());
+    return Err(extends_error(format!("Circular `extends` in config: {}", cycle.join(" -> "))));
+  }
+  stack.push(canonical);
+
+  let mut value = read(path, warnings)?;
+  let config_dir = path.parent().unwrap_or(Path::new("."));
+  let mut merged = Value::Mapping(Mapping::new());
+  for parent in extends_of(&value).map_err(extends_error)? {
+    let parent_path = resolve(&parent, config_dir).map_err(extends_error)?;
+    merged = merge(merged, load_with(&parent_path, stack, warnings, files)?);
+  }
+  if let Value::Mapping(mapping) = &mut value {
+    mapping.shift_remove(EXTENDS_KEY);
+  }
+
+  stack.pop();
+  files.push(path.to_path_buf());
+  Ok(merge(merged, value))
+}
+
+/**
+ * read a config file with the configs it extends merged in, later parents win
+ * over earlier ones and the file itself wins over all of them, unknown keys of
+ * every file are collected into `warnings` and the files read into `files`,
+ * from the least to the most important one
+ */
+pub fn load(path: &Path, warnings: &mut Vec<ConfigIssue>, files: &mut Vec<PathBuf>) -> Result<Value, ConfigIssue> {
+  load_with(path, &mut vec![], warnings, files)
+}
+
+/* ------------ test ------------ */
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn yaml(content: &str) -> Value {
+    serde_yaml::from_str(content).unwrap()
+  }
+
+  #[test]
+  fn test_merge() {
+    let parent = yaml(
+      "hooks:
+  enabled: true
+  hooks:
+    pre-push: npm test
+    post-merge: npm i
+staged_hooks:
+  rules:
+    '*.ts': eslint
+    '*.css': stylelint
+commit_msg:
+  validTypes: [feat, fix]
+",
+    );
+    let child = yaml(
+      "hooks:
+  hooks:
+    pre-push: [npm run build, npm test]
+    post-merge: null
+staged_hooks:
+  enabled: true
+  rules:
+    '*.ts':
+      commands: biome check
+commit_msg:
+  validTypes: [feat]
+",
+    );
+    let expected = yaml(
+      "hooks:
+  enabled: true
+  hooks:
+    pre-push: [npm run build, npm test]
+staged_hooks:
+  rules:
+    '*.ts':
+      commands: biome check
+    '*.css': stylelint
+  enabled: true
+commit_msg:
+  validTypes: [feat]
+",
+    );
+    let merged = merge(parent, child);
+    let rules = merged["staged_hooks"]["rules"].as_mapping().unwrap();
+    assert_eq!(rules.keys().collect::<Vec<&Value>>(), vec!["*.ts", "*.css"]);
+    assert_eq!(merged, expected);
+
+    let parent = yaml("staged_hooks:\n  rules:\n    - pattern: '*.ts'\n      commands: eslint\n");
+    let child = yaml("staged_hooks:\n  rules:\n    - pattern: '*.md'\n      commands: prettier\n");
+    let merged = merge(parent, child);
+    assert_eq!(merged["staged_hooks"]["rules"].as_sequence().unwrap().len(), 2);
+  }
+
+  #[test]
+  fn test_load_extends() {
+    let dir = std::env::temp_dir().join("igit_load_extends");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("repo/.config")).unwrap();
+    fs::create_dir_all(dir.join("node_modules/@team/igit-config")).unwrap();
+    fs::write(
+      dir.join("node_modules/@team/igit-config/igit.yaml"),
+      "commit_msg:\n  enabled: true\n  validTypes: [feat, fix]\n",
+    )
+    .unwrap();
+    fs::write(dir.join("repo/base.yaml"), "hooks:\n  enabled: true\n").unwrap();
+    fs::write(
+      dir.join("repo/.config/igit.yaml"),
+      "extends: [../base.yaml, '@team/igit-config']\ncommit_msg:\n  prependEmoji: true\n",
+    )
+    .unwrap();
+    let mut warnings = vec![];
+    let value = load(&dir.join("repo/.config/igit.yaml"), &mut warnings, &mut vec![]).unwrap();
+    assert!(warnings.is_empty());
+    assert_eq!(
+      value,
+      yaml("hooks:\n  enabled: true\ncommit_msg:\n  enabled: true\n  validTypes: [feat, fix]\n  prependEmoji: true\n")
+    );
+
+    fs::write(dir.join("repo/base.yaml"), "extends: ./.config/igit.yaml\n").unwrap();
+    let error = load(&dir.join("repo/.config/igit.yaml"), &mut warnings, &mut vec![]).unwrap_err();
+    assert_eq!(error.key.as_deref(), Some("extends"));
+    assert!(error.message.starts_with("Circular `extends` in config:"));
+
+    fs::write(dir.join("repo/base.yaml"), "extends: missing-config\n").unwrap();
+    let error = load(&dir.join("repo/.config/igit.yaml"), &mut warnings, &mut vec![]).unwrap_err();
+    assert!(error.message.starts_with("Can not find the config `missing-config`"));
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
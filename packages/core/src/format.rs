@@ -0,0 +1,137 @@
+use crate::diagnostic::{self, ConfigIssue};
+use serde_yaml::{Mapping, Value};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+  Yaml,
+  Toml,
+  Json,
+}
+
+impl Format {
+  /**
+   * format of a config file from its extension, yaml when it is not known
+   */
+  pub fn of(path: &Path) -> Self {
+    match path.extension().and_then(|ext| ext.to_str()) {
+      Some("toml") => Format::Toml,
+      Some("json") => Format::Json,
+      _ => Format::Yaml,
+    }
+  }
+}
+
+/**
+ * key paths of the config inside manifests shared with other tools, empty for igit files
+ */
+pub fn embedded_keys(path: &Path) -> &'static [&'static [&'static str]] {
+  match path.file_name().and_then(|name| name.to_str()) {
+    Some("package.json") => &[&["igit"]],
+    Some("Cargo.toml") => &[&["workspace", "metadata", "igit"], &["package", "metadata", "igit"]],
+    _ => &[],
+  }
+}
+
+fn line_column(content: &str, offset: usize) -> (u32, u32) {
+  let before = &content[..offset.min(content.len())];
+  let line = before.matches('\n').count() + 1;
+  let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+  (line as u32, column as u32)
+}
+
+/**
+ * whole document of a file as yaml value
+ */
+fn document(path: &Path, content: &str) -> Result<Value, ConfigIssue> {
+  let file = path.display().to_string();
+  let error = |message: String, location: Option<(u32, u32)>| {
+    let issue = ConfigIssue::error(&file, message);
+    match location {
+      Some((line, column)) => issue.at(line, column),
+      None => issue,
+    }
+  };
+  let value = match Format::of(path) {
+    Format::Yaml => return serde_yaml::from_str(content).map_err(|e| diagnostic::yaml_error(&file, &e)),
+    Format::Toml => {
+      let value: toml::Value = toml::from_str(content)
+        .map_err(|e| error(e.message().to_string(), e.span().map(|span| line_column(content, span.start))))?;
+      serde_yaml::to_value(value)
+    }
+    Format::Json => {
+      let value: serde_json::Value = serde_json::from_str(content)
+        .map_err(|e| error(diagnostic::without_location(e.to_string()), Some((e.line() as u32, e.column() as u32))))?;
+      serde_yaml::to_value(value)
+    }
+  };
+  value.map_err(|e| error(e.to_string(), None))
+}
+
+/**
+ * the config in a file as yaml value with the key path it was found at,
+ * `None` for manifests without an igit config
+ */
+pub fn parse(path: &Path, content: &str) -> Result<Option<(Value, &'static [&'static str])>, ConfigIssue> {
+  let document = document(path, content)?;
+  let keys = embedded_keys(path);
+  if keys.is_empty() {
+    let value = if document.is_null() { Value::Mapping(Mapping::new()) } else { document };
+    return Ok(Some((value, &[])));
+  }
+  Ok(keys.iter().find_map(|key| {
+    let value = key.iter().try_fold(&document, |value, segment| value.get(segment))?;
+    Some((value.clone(), *key))
+  }))
+}
+
+/**
+ * whether a file holds an igit config, manifests only count when they have the igit key
+ */
+pub fn has_config(path: &Path) -> bool {
+  if !path.is_file() {
+    return false;
+  }
+  if embedded_keys(path).is_empty() {
+    return true;
+  }
+  fs::read_to_string(path)
+    .ok()
+    .and_then(|content| parse(path, &content).ok())
+    .is_some_and(|config| config.is_some())
+}
+
+/* ------------ test ------------ */
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn yaml(content: &str) -> Value {
+    serde_yaml::from_str(content).unwrap()
+  }
+
+  #[test]
+  fn test_parse_formats() {
+    let expected = yaml("hooks:\n  enabled: true\nstaged_hooks:\n  rules:\n    '*.rs': cargo fmt\n");
+    let toml = "[hooks]\nenabled = true\n\n[staged_hooks.rules]\n'*.rs' = 'cargo fmt'\n";
+    let json = r#"{ "hooks": { "enabled": true }, "staged_hooks": { "rules": { "*.rs": "cargo fmt" } } }"#;
+    let package = r#"{ "name": "app", "igit": { "hooks": { "enabled": true }, "staged_hooks": { "rules": { "*.rs": "cargo fmt" } } } }"#;
+    let cargo = "[package]\nname = 'app'\n\n[package.metadata.igit.hooks]\nenabled = true\n\n[package.metadata.igit.staged_hooks.rules]\n'*.rs' = 'cargo fmt'\n";
+    for (name, content) in [("igit.toml", toml), ("igit.json", json), ("package.json", package), ("Cargo.toml", cargo)] {
+      let (value, _) = parse(Path::new(name), content).unwrap().unwrap();
+      assert_eq!(value, expected, "{}", name);
+    }
+    assert_eq!(parse(Path::new("package.json"), r#"{ "name": "app" }"#).unwrap(), None);
+    assert_eq!(parse(Path::new("igit.yaml"), "").unwrap().unwrap().0, Value::Mapping(Mapping::new()));
+  }
+
+  #[test]
+  fn test_parse_syntax_errors() {
+    let error = parse(Path::new("igit.toml"), "[hooks]\nenabled = \n").unwrap_err();
+    assert_eq!((error.line, error.column), (Some(2), Some(11)));
+    let error = parse(Path::new("igit.json"), "{\n  \"hooks\": {\n    \"enabled\": tru }\n}\n").unwrap_err();
+    assert_eq!(error.line, Some(3));
+    assert!(error.is_error());
+  }
+}
@@ -1,4 +1,9 @@
-use std::{path::Path, process::Command};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
 
 pub fn is_git_installed() -> bool {
   Command::new("git")
@@ -11,8 +16,12 @@ pub fn is_git_installed() -> bool {
     .success()
 }
 
+/**
+ * whether the current directory is inside a working tree, `.git` is a file in linked
+ * worktrees and submodules so only git itself is trusted
+ */
 pub fn is_git_repo() -> bool {
-  let is_in_repo = Command::new("git")
+  Command::new("git")
     .arg("rev-parse")
     .arg("--is-inside-work-tree")
     .stdout(std::process::Stdio::null())
@@ -25,14 +34,376 @@ pub fn is_git_repo() -> bool {
       )
     })
     .unwrap()
-    .success();
+    .success()
+}
+
+/**
+ * run a git command and return its raw stdout
+ */
+pub fn run_raw<I, S>(args: I) -> Result<Vec<u8>, String>
+where
+  I: IntoIterator<Item = S>,
+  S: AsRef<std::ffi::OsStr>,
+{
+  let args = args
+    .into_iter()
+    .map(|arg| arg.as_ref().to_os_string())
+    .collect::<Vec<_>>();
+  let output = Command::new("git")
+    .args(&args)
+    .output()
+    .map_err(|e| format!("Failed to run git: {}", e))?;
+  if !output.status.success() {
+    let command = args
+      .iter()
+      .map(|arg| arg.to_string_lossy())
+      .collect::<Vec<_>>()
+      .join(" ");
+    return Err(format!(
+      "`git {}` failed: {}",
+      command,
+      String::from_utf8_lossy(&output.stderr).trim()
+    ));
+  }
+  Ok(output.stdout)
+}
+
+/**
+ * run a git command and return its trimmed stdout
+ */
+pub fn run<I, S>(args: I) -> Result<String, String>
+where
+  I: IntoIterator<Item = S>,
+  S: AsRef<std::ffi::OsStr>,
+{
+  let stdout = run_raw(args)?;
+  Ok(String::from_utf8_lossy(&stdout).trim().to_string())
+}
+
+/**
+ * run a git command from `dir`, pathspecs and paths in the output are relative to it
+ */
+pub fn run_in<I, S>(dir: &Path, args: I) -> Result<String, String>
+where
+  I: IntoIterator<Item = S>,
+  S: AsRef<std::ffi::OsStr>,
+{
+  let mut all_args = vec![OsString::from("-C"), dir.as_os_str().to_os_string()];
+  all_args.extend(args.into_iter().map(|arg| arg.as_ref().to_os_string()));
+  run(all_args)
+}
+
+/**
+ * resolve a path printed by git relative to the directory it ran in
+ */
+fn absolute(dir: &Path, path: String) -> PathBuf {
+  let path = PathBuf::from(path);
+  if path.is_absolute() {
+    return path;
+  }
+  let mut absolute = PathBuf::new();
+  for component in dir.join(path).components() {
+    match component {
+      Component::CurDir => {}
+      Component::ParentDir => {
+        absolute.pop();
+      }
+      component => absolute.push(component),
+    }
+  }
+  absolute
+}
+
+fn current_dir() -> Result<PathBuf, String> {
+  std::env::current_dir().map_err(|e| format!("Failed to get current directory: {}", e))
+}
+
+/**
+ * `core.commentChar`, `#` when it is not set, `auto` is kept for the caller to resolve
+ */
+pub fn comment_char() -> String {
+  run(["config", "core.commentChar"])
+    .ok()
+    .filter(|comment| !comment.is_empty())
+    .unwrap_or_else(|| "#".to_string())
+}
 
-  if !is_in_repo {
-    return false;
+/**
+ * how git cleans up the message of the commit being made, `commit.cleanup` or for `default`
+ * `strip` with an editor and `whitespace` without, git sets `GIT_EDITOR=:` for hooks
+ * of commits that do not open one
+ */
+pub fn commit_cleanup() -> String {
+  match run(["config", "commit.cleanup"]).unwrap_or_default().as_str() {
+    "" | "default" if std::env::var("GIT_EDITOR").is_ok_and(|editor| editor == ":") => "whitespace".to_string(),
+    "" | "default" => "strip".to_string(),
+    cleanup => cleanup.to_string(),
   }
-  let git_dir = Path::new(".git");
-  if !git_dir.exists() || !git_dir.is_dir() {
-    return false;
+}
+
+/**
+ * absolute path of the git directory of the current worktree
+ */
+pub fn git_dir() -> Result<PathBuf, String> {
+  run(["rev-parse", "--absolute-git-dir"]).map(PathBuf::from)
+}
+
+/**
+ * absolute path of the git directory shared by all worktrees
+ */
+fn common_dir(dir: &Path) -> Result<PathBuf, String> {
+  run_in(dir, ["rev-parse", "--git-common-dir"]).map(|path| absolute(dir, path))
+}
+
+/**
+ * absolute path of the hooks directory, a relative `core.hooksPath` is resolved
+ * against the working tree root like git does when running hooks
+ */
+pub fn hooks_dir() -> Result<PathBuf, String> {
+  hooks_dir_in(&current_dir()?)
+}
+
+fn hooks_dir_in(dir: &Path) -> Result<PathBuf, String> {
+  if let Ok(hooks_path) = run_in(dir, ["config", "--path", "--get", "core.hooksPath"]) {
+    if !hooks_path.is_empty() {
+      let hooks_path = PathBuf::from(hooks_path);
+      if hooks_path.is_absolute() {
+        return Ok(hooks_path);
+      }
+      let root = run_in(dir, ["rev-parse", "--show-toplevel"])?;
+      return Ok(PathBuf::from(root).join(hooks_path));
+    }
+  }
+  // hooks of linked worktrees live in the common git directory
+  Ok(common_dir(dir)?.join("hooks"))
+}
+
+/**
+ * absolute path of the working tree root
+ */
+pub fn root() -> Result<PathBuf, String> {
+  run(["rev-parse", "--show-toplevel"]).map(PathBuf::from)
+}
+
+pub fn has_head() -> bool {
+  run(["rev-parse", "--verify", "--quiet", "HEAD"]).is_ok()
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+  Added,
+  Modified,
+  Renamed,
+  Copied,
+  Deleted,
+  #[serde(rename = "typechange")]
+  TypeChanged,
+  Unmerged,
+}
+
+impl ChangeKind {
+  fn from_status(status: &str) -> Option<Self> {
+    match status.chars().next()? {
+      'A' => Some(ChangeKind::Added),
+      'M' => Some(ChangeKind::Modified),
+      'R' => Some(ChangeKind::Renamed),
+      'C' => Some(ChangeKind::Copied),
+      'D' => Some(ChangeKind::Deleted),
+      'T' => Some(ChangeKind::TypeChanged),
+      'U' => Some(ChangeKind::Unmerged),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StagedFile {
+  /// path relative to the root, kept as bytes so any file name survives
+  pub path: OsString,
+  pub change: ChangeKind,
+}
+
+/**
+ * path printed by git, file names are bytes on unix and UTF-8 elsewhere
+ */
+pub fn path_from_bytes(bytes: &[u8]) -> OsString {
+  #[cfg(unix)]
+  {
+    use std::os::unix::ffi::OsStrExt;
+    OsStr::from_bytes(bytes).to_os_string()
+  }
+  #[cfg(not(unix))]
+  {
+    OsString::from(String::from_utf8_lossy(bytes).to_string())
+  }
+}
+
+/**
+ * bytes of a path, for matching and quoting
+ */
+pub fn path_bytes(path: &OsStr) -> Cow<'_, [u8]> {
+  #[cfg(unix)]
+  {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(path.as_bytes())
+  }
+  #[cfg(not(unix))]
+  {
+    match path.to_string_lossy() {
+      Cow::Borrowed(path) => Cow::Borrowed(path.as_bytes()),
+      Cow::Owned(path) => Cow::Owned(path.into_bytes()),
+    }
+  }
+}
+
+/**
+ * paths of `-z` output
+ */
+fn split_paths(output: &[u8]) -> Vec<OsString> {
+  output
+    .split(|&b| b == 0)
+    .filter(|p| !p.is_empty())
+    .map(path_from_bytes)
+    .collect()
+}
+
+/**
+ * parse `git diff --name-status -z`, renames and copies report the new path
+ */
+fn parse_name_status(output: &[u8]) -> Vec<StagedFile> {
+  let mut files = vec![];
+  let mut tokens = output.split(|&b| b == 0).filter(|t| !t.is_empty());
+  while let Some(status) = tokens.next() {
+    let status = String::from_utf8_lossy(status);
+    // renames and copies list the old path first
+    if status.starts_with(['R', 'C']) {
+      tokens.next();
+    }
+    let Some(path) = tokens.next() else {
+      break;
+    };
+    // the paths of unknown statuses are read all the same to stay in step
+    let Some(change) = ChangeKind::from_status(&status) else {
+      continue;
+    };
+    files.push(StagedFile {
+      path: path_from_bytes(path),
+      change,
+    });
+  }
+  files
+}
+
+/**
+ * staged files with their kind of change
+ */
+pub fn staged_files() -> Result<Vec<StagedFile>, String> {
+  let output = run_raw([
+    "-c",
+    "core.quotePath=false",
+    "diff",
+    "--cached",
+    "--name-status",
+    "-z",
+  ])
+  .map_err(|e| format!("Failed to get staged files: {}", e))?;
+  Ok(parse_name_status(&output))
+}
+
+/**
+ * files with unstaged changes
+ */
+pub fn unstaged_files() -> Result<Vec<OsString>, String> {
+  let output = run_raw(["-c", "core.quotePath=false", "diff", "--name-only", "-z"])?;
+  Ok(split_paths(&output))
+}
+
+/* ------------ test ------------ */
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+
+  fn file(path: &str, change: ChangeKind) -> StagedFile {
+    StagedFile {
+      path: path.into(),
+      change,
+    }
+  }
+
+  #[test]
+  fn test_parse_name_status() {
+    let output = b"M\0src/a.ts\0D\0old.ts\0R087\0from.ts\0to file.ts\0A\0new\nline.ts\0";
+    assert_eq!(
+      parse_name_status(output),
+      vec![
+        file("src/a.ts", ChangeKind::Modified),
+        file("old.ts", ChangeKind::Deleted),
+        file("to file.ts", ChangeKind::Renamed),
+        file("new\nline.ts", ChangeKind::Added),
+      ]
+    );
+    assert_eq!(
+      parse_name_status(b"X\0Makefile\0B\0Attic/x\0M\0a.rs\0"),
+      vec![file("a.rs", ChangeKind::Modified)]
+    );
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_parse_non_utf8_paths() {
+    let files = parse_name_status(b"A\0bad\xff.ts\0");
+    assert_eq!(path_bytes(&files[0].path).as_ref(), b"bad\xff.ts");
+    assert_eq!(split_paths(b"a\0bad\xff\0").len(), 2);
+  }
+
+  #[test]
+  fn test_parse_empty_name_status() {
+    assert!(parse_name_status(b"").is_empty());
+  }
+
+  #[test]
+  fn test_hooks_dir() {
+    let dir = std::env::temp_dir().join("igit_hooks_dir");
+    let _ = fs::remove_dir_all(&dir);
+    let repo = dir.join("repo");
+    let worktree = dir.join("worktree");
+    fs::create_dir_all(&repo).unwrap();
+    let repo = repo.canonicalize().unwrap();
+    run_in(&repo, ["init", "--quiet"]).unwrap();
+    run_in(&repo, ["-c", "user.name=igit", "-c", "user.email=igit@example.com", "commit", "--quiet", "--allow-empty", "-m", "init"]).unwrap();
+    run_in(&repo, [OsStr::new("worktree"), OsStr::new("add"), OsStr::new("--quiet"), worktree.as_os_str()]).unwrap();
+    let worktree_sub = worktree.join("sub");
+    fs::create_dir_all(&worktree_sub).unwrap();
+    let git_dir = repo.join(".git");
+
+    // linked worktrees share the hooks of the main repository
+    assert_eq!(hooks_dir_in(&repo).unwrap(), git_dir.join("hooks"));
+    assert_eq!(hooks_dir_in(&worktree_sub).unwrap(), git_dir.join("hooks"));
+
+    // submodules keep their git dir, and hooks, in the modules of the superproject
+    let superproject = dir.join("superproject");
+    fs::create_dir_all(&superproject).unwrap();
+    let superproject = superproject.canonicalize().unwrap();
+    run_in(&superproject, ["init", "--quiet"]).unwrap();
+    let add = [OsStr::new("-c"), OsStr::new("protocol.file.allow=always"), OsStr::new("submodule"), OsStr::new("add"), OsStr::new("--quiet")];
+    run_in(&superproject, add.iter().copied().chain([repo.as_os_str(), OsStr::new("lib")])).unwrap();
+    let submodule_sub = superproject.join("lib").join("src");
+    fs::create_dir_all(&submodule_sub).unwrap();
+    assert!(superproject.join("lib").join(".git").is_file());
+    assert_eq!(
+      hooks_dir_in(&submodule_sub).unwrap(),
+      superproject.join(".git").join("modules").join("lib").join("hooks")
+    );
+
+    // a relative `core.hooksPath` is relative to the root of the working tree
+    run_in(&repo, ["config", "core.hooksPath", ".husky"]).unwrap();
+    assert_eq!(hooks_dir_in(&repo).unwrap(), repo.join(".husky"));
+    assert_eq!(
+      hooks_dir_in(&worktree_sub).unwrap(),
+      worktree.canonicalize().unwrap().join(".husky")
+    );
+    fs::remove_dir_all(&dir).unwrap();
   }
-  true
 }
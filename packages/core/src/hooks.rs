@@ -1,96 +1,34 @@
 use crate::command::ShellCommand;
+use crate::commit_lint;
 use crate::config;
 use crate::config::HookCommand;
+use crate::git;
 use crate::log::LOG_PREFIX;
-use fast_glob::glob_match;
+use crate::runner::{self, RunReport};
 use std::error::Error;
 use std::fs;
-use std::path::Path;
-use std::process::Command;
-
-pub fn install() -> Result<(), Box<dyn Error>> {
-  let config = config::check()?;
-  let git_config_output = Command::new("git")
-    .arg("config")
-    .arg("--get")
-    .arg("core.hooksPath")
-    .output()
-    .expect("Failed to get git hooks path");
-
-  let git_hooks_path = if git_config_output.status.success() {
-    String::from_utf8(git_config_output.stdout)?
-      .trim()
-      .to_string()
-  } else {
-    String::from(".git/hooks")
-  };
-
-  let hooks_dir = Path::new(&git_hooks_path);
-  if !hooks_dir.exists() {
-    fs::create_dir_all(hooks_dir)?;
-  }
-
-  let mut target_hooks = Vec::new();
-  if config.commit_msg.enabled {
-    target_hooks.push("commit-msg");
-  }
-  if config.staged_hooks.enabled {
-    target_hooks.push("pre-commit");
-  }
-  if config.hooks.enabled {
-    // all hooks
-    for hook_name in config.hooks.hooks.keys() {
-      if !target_hooks.contains(&hook_name.as_str()) {
-        target_hooks.push(hook_name);
-      }
-    }
-  }
-
-  // clear hooks
-  let entries = fs::read_dir(hooks_dir)?;
-  for entry in entries {
-    let entry = entry?;
-    let path = entry.path();
-    if path.is_file() && path.extension().is_none() {
-      fs::remove_file(path)?;
-    }
-  }
-
-  // generate hooks
-  for hook_name in target_hooks.iter() {
-    let hook_path = hooks_dir.join(hook_name);
-    let hook_content = format!(
-      r#"#!/usr/bin/env sh
-npx igit run "{}" "$@"
-"#,
-      hook_name
-    );
-    fs::write(&hook_path, hook_content)?;
-    Command::new("chmod").arg("+x").arg(&hook_path).output()?;
-  }
-  println!("{}Hooks installed", LOG_PREFIX);
-  Ok(())
-}
 
 #[derive(Debug)]
 #[allow(dead_code)]
-struct CommitMessage {
-  commit_type: String,
-  scope: Option<String>,
-  is_breaking: bool,
-  description: String,
-  body: Option<String>,
-  footers: Option<Vec<CommitMessageFooter>>,
+pub struct CommitMessage {
+  pub commit_type: String,
+  pub scope: Option<String>,
+  pub is_breaking: bool,
+  pub description: String,
+  pub body: Option<String>,
+  pub footers: Option<Vec<CommitMessageFooter>>,
+  /// index of the line the footers start at
+  pub footer_line: Option<usize>,
 }
 
 #[derive(Debug)]
 #[allow(dead_code)]
-struct CommitMessageFooter {
+pub struct CommitMessageFooter {
   key: String,
   value: String,
 }
 
-static DEFAULT_VALID_TYPES: &[&str] = &[
+pub static DEFAULT_VALID_TYPES: &[&str] = &[
   "build", "chore", "ci", "docs", "feat", "fix", "perf", "refactor", "revert", "style", "test",
 ];
 static DEFAULT_TYPES_EMOJI: &[&str] = &[
@@ -110,7 +48,7 @@ static UNKNOWN_TYPE_EMOJI: &str = "💡";
 *
 * [optional footer(s)]
 */
-fn parse_commit_message(commit_message: &str) -> Result<CommitMessage, String> {
+pub fn parse_commit_message(commit_message: &str) -> Result<CommitMessage, String> {
   let lines: Vec<&str> = commit_message.lines().collect();
   if lines.is_empty() {
     return Err("Commit message is empty".to_string());
@@ -138,6 +76,7 @@ fn parse_commit_message(commit_message: &str) -> Result<CommitMessage, String> {
 
   let mut body = None;
   let mut footers = Vec::new();
+  let mut footer_line = None;
 
   if lines.len() > 1 {
     let mut body_lines = Vec::new();
@@ -159,6 +98,10 @@ fn parse_commit_message(commit_message: &str) -> Result<CommitMessage, String> {
       }
     }
 
+    if footer_start_index < lines.len() {
+      footer_line = Some(footer_start_index);
+    }
+
     for line in &lines[1..footer_start_index] {
       body_lines.push(*line);
     }
@@ -186,6 +129,7 @@ fn parse_commit_message(commit_message: &str) -> Result<CommitMessage, String> {
     } else {
       Some(footers)
     },
+    footer_line,
   })
 }
 
@@ -200,7 +144,7 @@ fn append_emoji_for_message(message: &CommitMessage, original_message: &str) ->
   } else {
     UNKNOWN_TYPE_EMOJI
   };
-  let mut commit_type_with_scope = format!("{}", message.commit_type);
+  let mut commit_type_with_scope = message.commit_type.to_string();
   if let Some(scope) = &message.scope {
     commit_type_with_scope = format!("{}({})", commit_type_with_scope, scope);
   }
@@ -210,96 +154,39 @@ fn append_emoji_for_message(message: &CommitMessage, original_message: &str) ->
   )
 }
 
-/**
- * glob match
- */
-fn get_matched_files<'a>(pattern: &'a str, files: &Vec<&'a str>) -> Vec<&'a str> {
-  let mut matched_files = Vec::new();
-  for file in files.iter() {
-    if glob_match(&pattern, file) {
-      matched_files.push(*file);
-    }
-  }
-  matched_files
-}
-
-fn get_commands(command: &HookCommand) -> Vec<&str> {
+pub fn get_commands(command: &HookCommand) -> Vec<&str> {
   match command {
     HookCommand::Single(_command) => vec![_command.as_str()],
     HookCommand::Multiple(_commands) => _commands.iter().map(|s| s.as_str()).collect::<Vec<&str>>(),
   }
 }
 
-/**
- * collect staged commands
- */
-pub fn collect_staged_commands() -> Result<Vec<ShellCommand>, Box<dyn Error>> {
-  let config = config::check()?;
-  // no need to collect staged commands
-  if !config.staged_hooks.enabled || config.staged_hooks.rules.is_empty() {
-    return Ok(vec![]);
-  }
-  let mut staged_commands: Vec<ShellCommand> = vec![];
-  let staged_files = Command::new("git")
-    .arg("diff")
-    .arg("--cached")
-    .arg("--name-only")
-    .output()?;
-  if !staged_files.status.success() {
-    return Err("Failed to get staged files".into());
-  }
-  let files = String::from_utf8(staged_files.stdout)?;
-  let staged_files = files.trim().split('\n').collect::<Vec<&str>>();
-  for (pattern, command) in config.staged_hooks.rules.iter() {
-    let matched_files = get_matched_files(&pattern, &staged_files);
-    if !matched_files.is_empty() {
-      let commands = get_commands(command);
-      for command in commands {
-        println!("{}\x1b[90mRunning staged command:\x1b[0m \x1b[32m{}\x1b[0m \x1b[90mfor\x1b[0m \x1b[32m{}\x1b[0m \x1b[90mfiles that match pattern(\x1b[0m\x1b[34m{}\x1b[0m\x1b[90m)\x1b[0m", LOG_PREFIX, command, matched_files.len(), pattern);
-        staged_commands.push(ShellCommand::with_args(command.to_string(), matched_files.to_vec()));
-      }
-    }
-  }
-  Ok(staged_commands)
-}
-
 /**
  * collect hook commands
  */
-pub fn collect_hook_commands(hook_name: &str, args: &Vec<String>) -> Result<Vec<ShellCommand>, Box<dyn Error>> {
+pub fn collect_hook_commands(hook_name: &str, args: &[String]) -> Result<Vec<ShellCommand>, Box<dyn Error>> {
   let config = config::check()?;
   let mut collected_commands: Vec<ShellCommand> = vec![];
   // commit message hook
-  if hook_name == "commit-msg" {
-    if config.commit_msg.enabled {
-      let commit_message_path = &args[0];
-      let mut commit_message_str = fs::read_to_string(commit_message_path).map_err(|e| {
-        format!(
-          "Failed to read commit message from {}: {}",
-          commit_message_path, e
-        )
-      })?;
-      commit_message_str = commit_message_str.trim().to_string();
-      let commit_message = parse_commit_message(&commit_message_str)?;
-      // is valid commit type
-      let valid_types = if config.commit_msg.valid_types.is_some() {
-        config.commit_msg.valid_types.unwrap()
-      } else {
-        // default valid types
-        DEFAULT_VALID_TYPES
-          .iter()
-          .map(|&s| s.to_string())
-          .collect::<Vec<String>>()
-      };
-      if !valid_types.contains(&commit_message.commit_type) {
-        return Err(format!("Invalid commit type: {}", commit_message.commit_type).into());
-      }
-      // prepend emoji
-      if config.commit_msg.prepend_emoji {
-        commit_message_str = append_emoji_for_message(&commit_message, &mut commit_message_str);
-        println!("{}\x1b[32mAppend emoji for commit message.\x1b[0m", LOG_PREFIX);
-        fs::write(commit_message_path, commit_message_str)?;
-      }
+  if hook_name == "commit-msg" && config.commit_msg.enabled {
+    let commit_message_path = &args[0];
+    let mut commit_message_str = fs::read_to_string(commit_message_path).map_err(|e| {
+      format!(
+        "Failed to read commit message from {}: {}",
+        commit_message_path, e
+      )
+    })?;
+    commit_message_str = commit_message_str.trim().to_string();
+    // comments and the diff of `git commit -v` are removed by git after the hook runs
+    let message = commit_lint::clean_message(&commit_message_str, &git::commit_cleanup(), &git::comment_char());
+    let commit_message = parse_commit_message(&message)?;
+    let violations = commit_lint::lint(&message, &commit_message, &config.commit_msg);
+    commit_lint::report(&violations)?;
+    // prepend emoji
+    if config.commit_msg.prepend_emoji {
+      commit_message_str = append_emoji_for_message(&commit_message, &commit_message_str);
+      println!("{}\x1b[32mAppend emoji for commit message.\x1b[0m", LOG_PREFIX);
+      fs::write(commit_message_path, commit_message_str)?;
     }
   }
   if let Some(script) = config.hooks.hooks.get(hook_name) {
@@ -307,10 +194,6 @@ pub fn collect_hook_commands(hook_name: &str, args: &Vec<String>) -> Result<Vec<
     if config.hooks.enabled {
       let commands = get_commands(script);
       for command in commands {
-        println!(
-          "{}\x1b[90mRunning\x1b[0m \x1b[34m{}\x1b[0m \x1b[90mhook:\x1b[0m \x1b[32m{}\x1b[0m",
-          LOG_PREFIX, hook_name, command
-        );
         collected_commands.push(ShellCommand::with_args(command, args.to_vec()));
       }
     }
@@ -318,6 +201,19 @@ pub fn collect_hook_commands(hook_name: &str, args: &Vec<String>) -> Result<Vec<
   Ok(collected_commands)
 }
 
+/**
+ * run hook commands
+ */
+pub fn run_hook_commands(hook_name: &str, args: &[String]) -> Result<RunReport, Box<dyn Error>> {
+  let commands = collect_hook_commands(hook_name, args)?;
+  Ok(runner::run_commands(&commands, |command| {
+    println!(
+      "{}\x1b[90mRunning\x1b[0m \x1b[34m{}\x1b[0m \x1b[90mhook:\x1b[0m \x1b[32m{}\x1b[0m",
+      LOG_PREFIX, hook_name, command.command
+    );
+  }))
+}
+
 /* ------------ test ------------ */
 #[cfg(test)]
 mod tests {
@@ -326,12 +222,15 @@ mod tests {
   #[test]
   fn test_color_print() {
     println!("\x1b[32mAppend emoji for message\x1b[0m");
-    println!("\x1b[90mRunning staged command:\x1b[0m \x1b[32m{}\x1b[0m \x1b[90mfor\x1b[0m \x1b[32m{}\x1b[0m \x1b[90mfiles that match pattern(\x1b[0m\x1b[34m{}\x1b[0m\x1b[90m)\x1b[0m", "biome check --write", 4, "**/*.{ts,tsx}");
+    let command = "biome check --write";
+    let pattern = "**/*.{ts,tsx}";
+    println!("\x1b[90mRunning staged command:\x1b[0m \x1b[32m{}\x1b[0m \x1b[90mfor\x1b[0m \x1b[32m{}\x1b[0m \x1b[90mfiles that match pattern(\x1b[0m\x1b[34m{}\x1b[0m\x1b[90m)\x1b[0m", command, 4, pattern);
+    let hook_name = "pre-commit";
+    let command = "echo hello";
     println!(
       "\x1b[90mRunning\x1b[0m \x1b[34m{}\x1b[0m \x1b[90mhook:\x1b[0m \x1b[32m{}\x1b[0m",
-      "pre-commit", "echo hello"
+      hook_name, command
     );
-    assert!(true);
   }
 
   #[test]
@@ -445,12 +344,12 @@ mod tests {
   fn test_append_emoji_for_message_with_valid_types() {
     let message_str = "feat: add new feature";
     let message = parse_commit_message(message_str).unwrap();
-    let result = append_emoji_for_message(&message, &message_str);
+    let result = append_emoji_for_message(&message, message_str);
     assert_eq!(result, "feat: ✨ add new feature");
 
     let message1_str = "fix: resolve bug";
     let message1 = parse_commit_message(message1_str).unwrap();
-    let result1 = append_emoji_for_message(&message1, &message1_str);
+    let result1 = append_emoji_for_message(&message1, message1_str);
     assert_eq!(result1, "fix: 🐛 resolve bug");
   }
 
@@ -458,39 +357,7 @@ mod tests {
   fn test_append_emoji_for_message_with_unknown_types() {
     let message_str = "unknown: update documentation";
     let message = parse_commit_message(message_str).unwrap();
-    let result = append_emoji_for_message(&message, &message_str);
+    let result = append_emoji_for_message(&message, message_str);
     assert_eq!(result, "unknown: 💡 update documentation");
   }
-
-  #[test]
-  fn test_get_matched_files() {
-    let files = vec![
-      "foo/bar/baz.css",
-      "src/main.rs",
-      "src/lib.rs",
-      "tests/test.rs",
-      "README.md",
-    ];
-
-    let matched = get_matched_files("**/*.rs", &files);
-    assert_eq!(matched.len(), 3);
-    assert!(matched.contains(&"src/main.rs"));
-    assert!(matched.contains(&"src/lib.rs"));
-    assert!(matched.contains(&"tests/test.rs"));
-
-    let matched = get_matched_files("**/*.{rs,md}", &files);
-    assert_eq!(matched.len(), 4);
-    assert!(matched.contains(&"src/main.rs"));
-    assert!(matched.contains(&"src/lib.rs"));
-    assert!(matched.contains(&"tests/test.rs"));
-    assert!(matched.contains(&"README.md"));
-
-    let matched = get_matched_files("*/*.css", &files);
-    assert_eq!(matched.len(), 0);
-
-    let matched = get_matched_files("!**/*.rs", &files);
-    assert_eq!(matched.len(), 2);
-    assert!(matched.contains(&"foo/bar/baz.css"));
-    assert!(matched.contains(&"README.md"));
-  }
 }
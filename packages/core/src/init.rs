@@ -0,0 +1,765 @@
+use crate::config;
+use crate::extends;
+use crate::format::{self, Format};
+use crate::install;
+use crate::log::LOG_PREFIX;
+use crate::schema;
+use indexmap::IndexMap;
+use napi_derive::napi;
+use serde_yaml::{Mapping, Value};
+use std::fs;
+use std::path::Path;
+
+static CONFIG_FILE: &str = ".config/igit.yaml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Preset {
+  Js,
+  Rust,
+  Python,
+  Polyglot,
+}
+
+impl Preset {
+  fn parse(name: &str) -> Result<Self, String> {
+    match name.to_lowercase().as_str() {
+      "js" | "ts" | "javascript" | "typescript" => Ok(Preset::Js),
+      "rust" | "rs" => Ok(Preset::Rust),
+      "python" | "py" => Ok(Preset::Python),
+      "polyglot" => Ok(Preset::Polyglot),
+      _ => Err(format!(
+        "Unknown preset `{}`, expected one of js, rust, python, polyglot",
+        name
+      )),
+    }
+  }
+
+  fn name(&self) -> &'static str {
+    match self {
+      Preset::Js => "js",
+      Preset::Rust => "rust",
+      Preset::Python => "python",
+      Preset::Polyglot => "polyglot",
+    }
+  }
+
+  /**
+   * linters used when none of the preset is installed
+   */
+  fn default_linters(&self) -> Vec<&'static str> {
+    match self {
+      Preset::Js => vec!["biome", "stylelint"],
+      Preset::Rust => vec!["rustfmt"],
+      Preset::Python => vec!["ruff"],
+      Preset::Polyglot => vec!["biome", "stylelint", "rustfmt", "ruff"],
+    }
+  }
+
+  fn includes(&self, preset: Preset) -> bool {
+    *self == Preset::Polyglot || *self == preset
+  }
+}
+
+struct Linter {
+  name: &'static str,
+  preset: Preset,
+  /// files whose presence means the linter is set up
+  config_files: &'static [&'static str],
+  /// packages installing the linter
+  packages: &'static [&'static str],
+  /// staged rules running the linter, commands of the same pattern run in order
+  rules: &'static [(&'static str, &'static str)],
+}
+
+static LINTERS: &[Linter] = &[
+  Linter {
+    name: "biome",
+    preset: Preset::Js,
+    config_files: &["biome.json", "biome.jsonc"],
+    packages: &["@biomejs/biome"],
+    rules: &[("**/*.{js,jsx,ts,tsx}", "biome check --write")],
+  },
+  Linter {
+    name: "eslint",
+    preset: Preset::Js,
+    config_files: &[
+      "eslint.config.js",
+      "eslint.config.mjs",
+      "eslint.config.cjs",
+      "eslint.config.ts",
+      ".eslintrc",
+      ".eslintrc.js",
+      ".eslintrc.cjs",
+      ".eslintrc.json",
+      ".eslintrc.yaml",
+      ".eslintrc.yml",
+    ],
+    packages: &["eslint"],
+    rules: &[("**/*.{js,jsx,ts,tsx}", "eslint --fix")],
+  },
+  Linter {
+    name: "prettier",
+    preset: Preset::Js,
+    config_files: &[
+      ".prettierrc",
+      ".prettierrc.json",
+      ".prettierrc.yaml",
+      ".prettierrc.yml",
+      ".prettierrc.js",
+      ".prettierrc.cjs",
+      ".prettierrc.mjs",
+      "prettier.config.js",
+      "prettier.config.cjs",
+      "prettier.config.mjs",
+    ],
+    packages: &["prettier"],
+    rules: &[("**/*.{js,jsx,ts,tsx,json,md,yaml,yml}", "prettier --write --ignore-unknown")],
+  },
+  Linter {
+    name: "stylelint",
+    preset: Preset::Js,
+    config_files: &[
+      ".stylelintrc",
+      ".stylelintrc.json",
+      ".stylelintrc.yaml",
+      ".stylelintrc.yml",
+      ".stylelintrc.js",
+      ".stylelintrc.cjs",
+      "stylelint.config.js",
+      "stylelint.config.cjs",
+      "stylelint.config.mjs",
+    ],
+    packages: &["stylelint"],
+    rules: &[("**/*.{css,scss,less,styl,stylus}", "stylelint --fix")],
+  },
+  Linter {
+    name: "rustfmt",
+    preset: Preset::Rust,
+    // rustfmt comes with cargo
+    config_files: &["rustfmt.toml", ".rustfmt.toml", "Cargo.toml"],
+    packages: &[],
+    // `cargo fmt` formats the whole crate whatever files it is given
+    rules: &[("**/*.rs", "rustfmt --edition 2021 {files}")],
+  },
+  Linter {
+    name: "ruff",
+    preset: Preset::Python,
+    config_files: &["ruff.toml", ".ruff.toml"],
+    packages: &["ruff"],
+    rules: &[("**/*.py", "ruff check --fix"), ("**/*.py", "ruff format")],
+  },
+];
+
+fn linter(name: &str) -> &'static Linter {
+  LINTERS
+    .iter()
+    .find(|linter| linter.name == name)
+    .expect("Unknown linter")
+}
+
+/**
+ * names of the dependencies of package.json, pyproject.toml and requirements files
+ */
+fn dependencies(root: &Path) -> Vec<String> {
+  let mut dependencies = vec![];
+  if let Ok(content) = fs::read_to_string(root.join("package.json")) {
+    if let Ok(package) = serde_json::from_str::<serde_json::Value>(&content) {
+      for key in ["dependencies", "devDependencies"] {
+        if let Some(packages) = package.get(key).and_then(|p| p.as_object()) {
+          dependencies.extend(packages.keys().cloned());
+        }
+      }
+    }
+  }
+  if let Ok(content) = fs::read_to_string(root.join("pyproject.toml")) {
+    if content.contains("[tool.ruff") {
+      dependencies.push("ruff".to_string());
+    }
+    // requirements in pyproject.toml are strings like `"ruff>=0.4"`
+    dependencies.extend(content.split('"').map(requirement_name));
+  }
+  for file in ["requirements.txt", "requirements-dev.txt"] {
+    if let Ok(content) = fs::read_to_string(root.join(file)) {
+      dependencies.extend(content.lines().map(requirement_name));
+    }
+  }
+  dependencies
+}
+
+fn requirement_name(requirement: &str) -> String {
+  requirement
+    .trim()
+    .split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_' || c == '.'))
+    .next()
+    .unwrap_or_default()
+    .to_string()
+}
+
+/**
+ * linters set up in the project, in the order of `LINTERS`
+ */
+fn detect_linters(root: &Path) -> Vec<&'static str> {
+  let dependencies = dependencies(root);
+  LINTERS
+    .iter()
+    .filter(|linter| {
+      linter.config_files.iter().any(|file| root.join(file).is_file())
+        || linter.packages.iter().any(|package| dependencies.iter().any(|d| d == package))
+    })
+    .map(|linter| linter.name)
+    .collect()
+}
+
+/**
+ * preset from the manifests of the project, js when there is none
+ */
+fn detect_preset(root: &Path) -> Preset {
+  let has = |files: &[&str]| files.iter().any(|file| root.join(file).is_file());
+  let presets = [
+    (Preset::Js, has(&["package.json"])),
+    (Preset::Rust, has(&["Cargo.toml"])),
+    (Preset::Python, has(&["pyproject.toml", "setup.py", "requirements.txt"])),
+  ]
+  .into_iter()
+  .filter(|(_, found)| *found)
+  .map(|(preset, _)| preset)
+  .collect::<Vec<Preset>>();
+  match presets[..] {
+    [] => Preset::Js,
+    [preset] => preset,
+    _ => Preset::Polyglot,
+  }
+}
+
+/**
+ * linters of the preset installed in the project, or the preset defaults
+ */
+fn preset_linters(preset: Preset, detected: &[&'static str]) -> Vec<&'static str> {
+  let linters = detected
+    .iter()
+    .copied()
+    .filter(|name| preset.includes(linter(name).preset))
+    .collect::<Vec<&str>>();
+  if linters.is_empty() {
+    preset.default_linters()
+  } else {
+    linters
+  }
+}
+
+fn quote(pattern: &str) -> String {
+  format!("'{}'", pattern.replace('\'', "''"))
+}
+
+/**
+ * yaml config generated for the linters
+ */
+fn template(linters: &[&str]) -> String {
+  let mut rules: IndexMap<&str, Vec<&str>> = IndexMap::new();
+  for name in linters {
+    for (pattern, command) in linter(name).rules {
+      rules.entry(pattern).or_default().push(command);
+    }
+  }
+  let mut staged_rules = String::new();
+  for (pattern, commands) in rules {
+    match commands[..] {
+      [command] => staged_rules.push_str(&format!("    {}: {}\n", quote(pattern), command)),
+      _ => {
+        staged_rules.push_str(&format!("    {}:\n", quote(pattern)));
+        for command in commands {
+          staged_rules.push_str(&format!("      - {}\n", command));
+        }
+      }
+    }
+  }
+  format!(
+    "# yaml-language-server: $schema={}
+hooks:
+  enabled: true
+  hooks: {{}}
+staged_hooks:
+  enabled: true
+  rules:
+{}commit_msg:
+  enabled: true
+  prependEmoji: true
+ai:
+  enabled: true
+  baseUrl: https://api.deepseek.com
+  # apiKey:
+  model: deepseek-chat
+  respondIn: English
+",
+    schema::url(),
+    staged_rules
+  )
+}
+
+/**
+ * content of a config file in its format, yaml keeps the comments of the template
+ */
+fn render(path: &Path, value: &Value, template: Option<&str>) -> Result<String, String> {
+  let content = match (Format::of(path), template) {
+    (Format::Yaml, Some(template)) => Ok(template.to_string()),
+    (Format::Yaml, None) => serde_yaml::to_string(value)
+      .map(|yaml| format!("# yaml-language-server: $schema={}\n{}", schema::url(), yaml))
+      .map_err(|e| e.to_string()),
+    (Format::Toml, _) => toml::to_string_pretty(value).map_err(|e| e.to_string()),
+    (Format::Json, _) => serde_json::to_string_pretty(value)
+      .map(|json| json + "\n")
+      .map_err(|e| e.to_string()),
+  };
+  content.map_err(|e| format!("Failed to write config {}: {}", path.display(), e))
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct InitOptions {
+  /// `js`, `rust`, `python` or `polyglot`, detected from the project when not set
+  pub preset: Option<String>,
+  /// overwrite an existing config
+  pub force: Option<bool>,
+  /// merge the generated config into an existing one, existing values win
+  pub merge: Option<bool>,
+  /// install the hooks afterwards
+  pub install: Option<bool>,
+}
+
+#[napi(object)]
+pub struct ProjectInfo {
+  pub preset: String,
+  /// linters set up in the project
+  pub linters: Vec<String>,
+  /// config already in the directory, relative to it
+  pub config_file: Option<String>,
+}
+
+#[napi(object)]
+pub struct InitReport {
+  pub config_file: String,
+  pub preset: String,
+  /// linters the staged rules were generated for
+  pub linters: Vec<String>,
+  /// false when a config already existed and was kept
+  pub written: bool,
+}
+
+/**
+ * preset and linters detected in a project
+ */
+pub fn detect(root: &Path) -> ProjectInfo {
+  ProjectInfo {
+    preset: detect_preset(root).name().to_string(),
+    linters: detect_linters(root).iter().map(|name| name.to_string()).collect(),
+    config_file: config::config_file_in(root).map(|path| path.strip_prefix(root).unwrap_or(&path).display().to_string()),
+  }
+}
+
+/**
+ * staged rules of the generated mapping as items of a list-shaped `rules`,
+ * rules the list already has are skipped
+ */
+fn rules_as_items(generated: Mapping, existing: &[Value]) -> Vec<Value> {
+  generated
+    .into_iter()
+    .map(|(pattern, commands)| {
+      let mut item = Mapping::new();
+      item.insert(Value::String("pattern".to_string()), pattern);
+      item.insert(Value::String("commands".to_string()), commands);
+      Value::Mapping(item)
+    })
+    .filter(|item| !existing.contains(item))
+    .collect()
+}
+
+/**
+ * add the keys of `generated` the resolved config does not have to `current`, the
+ * config as written. values set by the configs it extends are kept and keys
+ * removed with `key: null` stay removed
+ */
+fn add_missing(current: &mut Mapping, resolved: Option<&Mapping>, generated: Mapping, path: &[&str]) {
+  for (key, value) in generated {
+    if current.get(&key).is_some_and(Value::is_null) {
+      continue;
+    }
+    let name = key.as_str().unwrap_or_default().to_string();
+    let mut child_path = path.to_vec();
+    child_path.push(&name);
+    let existing = resolved.and_then(|resolved| resolved.get(&key));
+    match (existing, value) {
+      (None, value) => {
+        current.insert(key, value);
+      }
+      (Some(Value::Sequence(items)), Value::Mapping(rules)) if child_path == ["staged_hooks", "rules"] => {
+        let added = rules_as_items(rules, items);
+        if added.is_empty() {
+          continue;
+        }
+        // list-shaped rules of the configs extended are appended to, so only the new items are written
+        match current.get_mut(&key) {
+          Some(Value::Sequence(current_items)) => current_items.extend(added),
+          _ => {
+            current.insert(key, Value::Sequence(added));
+          }
+        }
+      }
+      (Some(Value::Mapping(existing)), Value::Mapping(generated)) => {
+        let mut child = match current.get(&key) {
+          Some(Value::Mapping(child)) => child.clone(),
+          _ => Mapping::new(),
+        };
+        add_missing(&mut child, Some(existing), generated, &child_path);
+        if !child.is_empty() {
+          current.insert(key, Value::Mapping(child));
+        }
+      }
+      _ => {}
+    }
+  }
+}
+
+/**
+ * what the merge added to `original`, new entries of a mapping or new items of a list
+ * along with the path of the mapping or list
+ */
+fn additions(original: &Mapping, merged: &Mapping, path: &[String], found: &mut Vec<(Vec<String>, Value)>) {
+  let mut entries = Mapping::new();
+  for (key, value) in merged {
+    let mut child_path = path.to_vec();
+    child_path.push(key.as_str().unwrap_or_default().to_string());
+    match (original.get(key), value) {
+      (None, value) => {
+        entries.insert(key.clone(), value.clone());
+      }
+      (Some(Value::Mapping(original)), Value::Mapping(merged)) => additions(original, merged, &child_path, found),
+      (Some(Value::Sequence(original)), Value::Sequence(merged)) if merged.len() > original.len() => {
+        found.push((child_path, Value::Sequence(merged[original.len()..].to_vec())));
+      }
+      _ => {}
+    }
+  }
+  if !entries.is_empty() {
+    found.push((path.to_vec(), Value::Mapping(entries)));
+  }
+}
+
+fn indent_of(line: &str) -> usize {
+  line.len() - line.trim_start().len()
+}
+
+fn is_content(line: &str) -> bool {
+  let line = line.trim();
+  !line.is_empty() && !line.starts_with('#')
+}
+
+/**
+ * write `value` at the end of the yaml block at `path`, the other lines are kept as written.
+ * `None` when a key of the path is not written in the block style
+ */
+fn insert_yaml(content: &str, path: &[String], value: &Value) -> Option<String> {
+  let lines = content.lines().collect::<Vec<&str>>();
+  // lines of the block and the indent of its key, the whole document has no key
+  let (mut start, mut end, mut key_indent) = (0, lines.len(), None);
+  for key in path {
+    let indent = (start..end).find(|&i| is_content(lines[i])).map(|i| indent_of(lines[i]))?;
+    let index = (start..end).find(|&i| {
+      let rest = lines[i].trim_start().strip_prefix(key.as_str()).and_then(|rest| rest.strip_prefix(':'));
+      indent_of(lines[i]) == indent && rest.is_some_and(|rest| rest.trim().is_empty() || rest.trim_start().starts_with('#'))
+    })?;
+    // items of a list may be written at the indent of its key
+    let ends = |line: &str| {
+      is_content(line) && (indent_of(line) < indent || indent_of(line) == indent && !line.trim_start().starts_with('-'))
+    };
+    start = index + 1;
+    end = (start..end).find(|&i| ends(lines[i])).unwrap_or(end);
+    key_indent = Some(indent);
+  }
+  let last = (start..end).rev().find(|&i| is_content(lines[i]));
+  let indent = match last {
+    Some(_) => (start..end).find(|&i| is_content(lines[i])).map(|i| indent_of(lines[i]))?,
+    None => key_indent.map_or(0, |indent| indent + 2),
+  };
+  let position = last.map_or(start, |last| last + 1);
+  let yaml = serde_yaml::to_string(value).ok()?;
+  let inserted = yaml.lines().map(|line| match line {
+    "" => String::new(),
+    line => format!("{}{}", " ".repeat(indent), line),
+  });
+  let mut merged = lines[..position].iter().map(|line| line.to_string()).collect::<Vec<String>>();
+  merged.extend(inserted);
+  merged.extend(lines[position..].iter().map(|line| line.to_string()));
+  Some(merged.join("\n") + "\n")
+}
+
+/**
+ * add what the merge added to the yaml text, so its comments and key order are kept
+ */
+fn merge_yaml(path: &Path, content: &str, original: &Mapping, merged: &Mapping) -> Result<String, String> {
+  let mut found = vec![];
+  additions(original, merged, &[], &mut found);
+  found.into_iter().try_fold(content.to_string(), |content, (key, value)| {
+    insert_yaml(&content, &key, &value).ok_or_else(|| {
+      format!(
+        "Can not merge into `{}` of {} as it is not written in the block style, add {} by hand",
+        key.join("."),
+        path.display(),
+        serde_yaml::to_string(&value).unwrap_or_default().trim()
+      )
+    })
+  })
+}
+
+fn init_in(root: &Path, options: &InitOptions) -> Result<InitReport, String> {
+  let force = options.force.unwrap_or(false);
+  let merge = options.merge.unwrap_or(false);
+  if force && merge {
+    return Err("Use either `force` or `merge`".to_string());
+  }
+  let preset = match &options.preset {
+    Some(name) => Preset::parse(name)?,
+    None => detect_preset(root),
+  };
+  let linters = preset_linters(preset, &detect_linters(root));
+  let template = template(&linters);
+  let generated: Value = serde_yaml::from_str(&template).map_err(|e| e.to_string())?;
+
+  let existing = config::config_file_in(root);
+  // manifests shared with other tools are never rewritten, a config file takes precedence over them
+  let is_manifest = existing.as_deref().is_some_and(|path| !format::embedded_keys(path).is_empty());
+  let mut report = InitReport {
+    config_file: CONFIG_FILE.to_string(),
+    preset: preset.name().to_string(),
+    linters: linters.iter().map(|name| name.to_string()).collect(),
+    written: true,
+  };
+  let (path, content, action) = match existing {
+    Some(path) if merge && is_manifest => {
+      return Err(format!(
+        "Can not merge into {}, edit its igit config instead",
+        path.display()
+      ))
+    }
+    Some(path) if merge => {
+      let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read config {}: {}", path.display(), e))?;
+      let (mut current, _) = format::parse(&path, &content)
+        .map_err(|e| format!("Invalid config {}", e))?
+        .unwrap_or_default();
+      let resolved = extends::load(&path, &mut vec![], &mut vec![]).map_err(|e| format!("Invalid config {}", e))?;
+      if current.is_null() {
+        current = Value::Mapping(Mapping::new());
+      }
+      let original = current.clone();
+      if let (Value::Mapping(current), Value::Mapping(generated)) = (&mut current, generated) {
+        add_missing(current, resolved.as_mapping(), generated, &[]);
+      }
+      let content = match (Format::of(&path), &original, &current) {
+        (Format::Yaml, Value::Mapping(original), Value::Mapping(merged)) => merge_yaml(&path, &content, original, merged)?,
+        _ => render(&path, &current, None)?,
+      };
+      (path, content, "Merged into")
+    }
+    Some(path) if force && !is_manifest => {
+      let content = render(&path, &generated, Some(&template))?;
+      (path, content, "Overwrote")
+    }
+    Some(path) if !force => {
+      report.config_file = path.strip_prefix(root).unwrap_or(&path).display().to_string();
+      report.written = false;
+      println!(
+        "{}Config {} already exists, use `--force` to overwrite it or `--merge` to merge into it",
+        LOG_PREFIX, report.config_file
+      );
+      return Ok(report);
+    }
+    _ => (root.join(CONFIG_FILE), template, "Created"),
+  };
+
+  if let Some(dir) = path.parent() {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+  }
+  fs::write(&path, content).map_err(|e| format!("Failed to write config {}: {}", path.display(), e))?;
+  report.config_file = path.strip_prefix(root).unwrap_or(&path).display().to_string();
+  println!(
+    "{}{} \x1b[34m{}\x1b[0m with the {} preset ({})",
+    LOG_PREFIX,
+    action,
+    report.config_file,
+    report.preset,
+    report.linters.join(", ")
+  );
+  Ok(report)
+}
+
+/**
+ * create the config of the current directory from a preset
+ */
+pub fn init(options: InitOptions) -> Result<InitReport, String> {
+  let current_dir = std::env::current_dir().map_err(|e| format!("Failed to get current directory: {}", e))?;
+  let report = init_in(&current_dir, &options)?;
+  if options.install.unwrap_or(false) {
+    install::install().map_err(|e| e.to_string())?;
+  }
+  Ok(report)
+}
+
+/* ------------ test ------------ */
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::path::PathBuf;
+
+  fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (file, content) in files {
+      fs::write(dir.join(file), content).unwrap();
+    }
+    dir
+  }
+
+  #[test]
+  fn test_detect() {
+    let dir = project(
+      "igit_init_detect",
+      &[
+        ("package.json", r#"{ "devDependencies": { "eslint": "^9.0.0", "prettier": "^3.0.0" } }"#),
+        ("pyproject.toml", "[project]\ndependencies = [\"ruff>=0.4\"]\n"),
+      ],
+    );
+    let info = detect(&dir);
+    assert_eq!(info.preset, "polyglot");
+    assert_eq!(info.linters, vec!["eslint", "prettier", "ruff"]);
+    assert_eq!(preset_linters(Preset::Js, &detect_linters(&dir)), vec!["eslint", "prettier"]);
+    assert_eq!(preset_linters(Preset::Rust, &detect_linters(&dir)), vec!["rustfmt"]);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_template() {
+    let template = template(&["biome", "eslint", "ruff"]);
+    assert!(template.contains(
+      "  rules:
+    '**/*.{js,jsx,ts,tsx}':
+      - biome check --write
+      - eslint --fix
+    '**/*.py':
+      - ruff check --fix
+      - ruff format
+commit_msg:"
+    ));
+    let value: Value = serde_yaml::from_str(&template).unwrap();
+    assert!(config::from_value(value).is_ok());
+  }
+
+  #[test]
+  fn test_init_force_and_merge() {
+    let dir = project("igit_init_merge", &[("igit.toml", "[commit_msg]\nenabled = false\n")]);
+    let options = |force: bool, merge: bool| InitOptions {
+      preset: Some("rust".to_string()),
+      force: Some(force),
+      merge: Some(merge),
+      install: None,
+    };
+    let report = init_in(&dir, &options(false, false)).unwrap();
+    assert!(!report.written);
+    assert_eq!(report.config_file, "igit.toml");
+
+    let report = init_in(&dir, &options(false, true)).unwrap();
+    assert_eq!(report.config_file, "igit.toml");
+    let content = fs::read_to_string(dir.join("igit.toml")).unwrap();
+    let (merged, _) = format::parse(&dir.join("igit.toml"), &content).unwrap().unwrap();
+    assert_eq!(merged["commit_msg"]["enabled"], Value::Bool(false));
+    assert_eq!(merged["staged_hooks"]["rules"]["**/*.rs"], Value::String("rustfmt --edition 2021 {files}".to_string()));
+
+    init_in(&dir, &options(true, false)).unwrap();
+    let content = fs::read_to_string(dir.join("igit.toml")).unwrap();
+    assert!(content.contains("[commit_msg]\nenabled = true"));
+    assert!(init_in(&dir, &options(true, true)).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_init_merge_keeps_extended_values_and_null_keys() {
+    let dir = project(
+      "igit_init_merge_extends",
+      &[
+        (
+          "base.yaml",
+          "commit_msg:\n  enabled: true\n  prependEmoji: false\nai:\n  enabled: false\nstaged_hooks:\n  enabled: true\n  rules:\n    - pattern: '**/*.rs'\n      commands: cargo clippy\nhooks:\n  enabled: true\n  hooks:\n    post-merge: npm install\n",
+        ),
+        ("igit.yaml", "extends: ./base.yaml\nhooks:\n  hooks:\n    post-merge: null\n"),
+      ],
+    );
+    let options = InitOptions {
+      preset: Some("rust".to_string()),
+      merge: Some(true),
+      ..Default::default()
+    };
+    init_in(&dir, &options).unwrap();
+    let content = fs::read_to_string(dir.join("igit.yaml")).unwrap();
+    let (merged, _) = format::parse(&dir.join("igit.yaml"), &content).unwrap().unwrap();
+    assert_eq!(merged["hooks"]["hooks"]["post-merge"], Value::Null);
+    assert_eq!(merged["commit_msg"].get("prependEmoji"), None);
+    assert_eq!(merged["ai"].get("enabled"), None);
+    assert_eq!(merged["ai"]["model"], Value::String("deepseek-chat".to_string()));
+    assert_eq!(
+      merged["staged_hooks"]["rules"],
+      serde_yaml::from_str::<Value>("- pattern: '**/*.rs'\n  commands: rustfmt --edition 2021 {files}\n").unwrap()
+    );
+
+    let config = config::parse_file(&dir.join("igit.yaml")).unwrap();
+    assert!(config.hooks.hooks.is_empty());
+    assert!(!config.ai.enabled);
+    assert!(!config.commit_msg.prepend_emoji);
+    assert_eq!(config.staged_hooks.rules.ordered().len(), 2);
+
+    // nothing is added twice
+    init_in(&dir, &options).unwrap();
+    assert_eq!(fs::read_to_string(dir.join("igit.yaml")).unwrap(), content);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_init_merge_keeps_yaml_comments() {
+    let content = "# project hooks
+hooks:
+  enabled: true # on every commit
+  hooks:
+    pre-push: cargo test
+
+# staged
+staged_hooks:
+  enabled: true
+  rules:
+  - pattern: '**/*.md'
+    commands: prettier --write
+  # more rules later
+commit_msg:
+  enabled: false
+";
+    let dir = project("igit_init_merge_comments", &[("igit.yaml", content)]);
+    let options = InitOptions {
+      preset: Some("rust".to_string()),
+      merge: Some(true),
+      ..Default::default()
+    };
+    init_in(&dir, &options).unwrap();
+    let merged = fs::read_to_string(dir.join("igit.yaml")).unwrap();
+    let rules = "  - pattern: '**/*.md'\n    commands: prettier --write\n";
+    let expected = content
+      .replace(rules, &format!("{}  - pattern: '**/*.rs'\n    commands: rustfmt --edition 2021 {{files}}\n", rules))
+      .replace("  enabled: false\n", "  enabled: false\n  prependEmoji: true\n")
+      + "ai:\n  enabled: true\n  baseUrl: https://api.deepseek.com\n  model: deepseek-chat\n  respondIn: English\n";
+    assert_eq!(merged, expected);
+    init_in(&dir, &options).unwrap();
+    assert_eq!(fs::read_to_string(dir.join("igit.yaml")).unwrap(), expected);
+
+    // flow mappings can not be added to line by line
+    fs::write(dir.join("igit.yaml"), "commit_msg: { enabled: false }\n").unwrap();
+    let error = init_in(&dir, &options).err().unwrap();
+    assert!(error.contains("`commit_msg`"), "{}", error);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
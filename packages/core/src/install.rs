@@ -0,0 +1,807 @@
+use crate::command::quote_arg;
+use crate::config;
+use crate::config::{HookChain, IgitConfig};
+use crate::git;
+use crate::log::LOG_PREFIX;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// marks a hook file as generated by igit, only these files are ever replaced or removed
+pub static HOOK_MARKER: &str = "# igit: managed hook";
+static BACKUP_SUFFIX: &str = ".igit-backup";
+
+/**
+ * hooks igit should install for the config
+ */
+fn target_hooks(config: &IgitConfig) -> Vec<String> {
+  let mut target_hooks = Vec::new();
+  if config.commit_msg.enabled {
+    target_hooks.push("commit-msg".to_string());
+  }
+  if config.staged_hooks.enabled {
+    target_hooks.push("pre-commit".to_string());
+  }
+  if config.hooks.enabled {
+    // all hooks
+    for hook_name in config.hooks.hooks.keys() {
+      if !target_hooks.contains(hook_name) {
+        target_hooks.push(hook_name.to_string());
+      }
+    }
+  }
+  target_hooks
+}
+
+/**
+ * whether the content is the script igit wrote before hooks carried the marker
+ */
+fn is_legacy_hook(content: &str, hook_name: &str) -> bool {
+  let lines = content
+    .lines()
+    .map(str::trim)
+    .filter(|line| !line.is_empty())
+    .collect::<Vec<&str>>();
+  lines == ["#!/usr/bin/env sh", &format!("npx igit run \"{}\" \"$@\"", hook_name)]
+}
+
+/**
+ * whether the file is a hook generated by igit
+ */
+pub fn is_igit_hook(path: &Path) -> bool {
+  let hook_name = path.file_name().unwrap_or_default().to_string_lossy();
+  fs::read_to_string(path)
+    .map(|content| content.lines().any(|line| line.trim() == HOOK_MARKER) || is_legacy_hook(&content, &hook_name))
+    .unwrap_or(false)
+}
+
+/**
+ * hook files in the hooks dir, samples and backups have an extension and are skipped
+ */
+fn hook_files(hooks_dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+  let mut files = vec![];
+  for entry in fs::read_dir(hooks_dir)? {
+    let path = entry?.path();
+    if path.is_file() && path.extension().is_none() {
+      files.push(path);
+    }
+  }
+  files.sort();
+  Ok(files)
+}
+
+/**
+ * hook name and number of a backup file, `<hook>.igit-backup` is number 0 and
+ * later backups are `<hook>.igit-backup.<n>`
+ */
+fn parse_backup_name(file_name: &str) -> Option<(&str, usize)> {
+  let start = file_name.rfind(BACKUP_SUFFIX)?;
+  let index = match &file_name[start + BACKUP_SUFFIX.len()..] {
+    "" => 0,
+    rest => rest.strip_prefix('.')?.parse().ok().filter(|index| *index > 0)?,
+  };
+  Some((&file_name[..start], index))
+}
+
+fn backup_file_name(hook_name: &str, index: usize) -> String {
+  match index {
+    0 => format!("{}{}", hook_name, BACKUP_SUFFIX),
+    index => format!("{}{}.{}", hook_name, BACKUP_SUFFIX, index),
+  }
+}
+
+/**
+ * numbers of the backups of a hook, oldest first
+ */
+fn backup_indexes(hook_path: &Path) -> Vec<usize> {
+  let hook_name = hook_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+  let Some(entries) = hook_path.parent().and_then(|dir| fs::read_dir(dir).ok()) else {
+    return vec![];
+  };
+  let mut indexes = entries
+    .filter_map(|entry| {
+      let file_name = entry.ok()?.file_name().to_string_lossy().to_string();
+      parse_backup_name(&file_name).filter(|(name, _)| *name == hook_name).map(|(_, index)| index)
+    })
+    .collect::<Vec<usize>>();
+  indexes.sort_unstable();
+  indexes
+}
+
+/**
+ * most recent backup of a hook and its number
+ */
+fn latest_backup_index(hook_path: &Path) -> Option<usize> {
+  backup_indexes(hook_path).last().copied()
+}
+
+/**
+ * most recent backup of a hook, the one chained by the installed hook and restored by uninstall
+ */
+fn latest_backup(hook_path: &Path) -> Option<PathBuf> {
+  let index = latest_backup_index(hook_path)?;
+  let hook_name = hook_path.file_name()?.to_string_lossy();
+  Some(hook_path.with_file_name(backup_file_name(&hook_name, index)))
+}
+
+/**
+ * path of the next backup of a hook, numbered after the most recent one
+ */
+fn backup_path(hook_path: &Path) -> PathBuf {
+  let hook_name = hook_path.file_name().unwrap_or_default().to_string_lossy();
+  let index = latest_backup_index(hook_path).map_or(0, |index| index + 1);
+  hook_path.with_file_name(backup_file_name(&hook_name, index))
+}
+
+/**
+ * backup the installed hook chains, a foreign hook in place is about to become the latest backup
+ */
+fn chained_backup(hook_path: &Path) -> PathBuf {
+  if hook_path.exists() && !is_igit_hook(hook_path) {
+    return backup_path(hook_path);
+  }
+  latest_backup(hook_path).unwrap_or_else(|| backup_path(hook_path))
+}
+
+/**
+ * write the hook through a temp file renamed over it, a hook that is running keeps
+ * reading its old script
+ */
+fn write_hook(hook_path: &Path, content: &str) -> Result<(), Box<dyn Error>> {
+  let name = hook_path.file_name().unwrap_or_default().to_string_lossy();
+  let temp_path = hook_path.with_file_name(format!(".{}.igit-tmp", name));
+  fs::write(&temp_path, content)?;
+  Command::new("chmod").arg("+x").arg(&temp_path).output()?;
+  if let Err(e) = fs::rename(&temp_path, hook_path) {
+    let _ = fs::remove_file(&temp_path);
+    return Err(e.into());
+  }
+  Ok(())
+}
+
+fn print_change(action: &str, hook_name: &str, detail: &str) {
+  println!(
+    "{}\x1b[90m{}\x1b[0m \x1b[34m{}\x1b[0m{}",
+    LOG_PREFIX, action, hook_name, detail
+  );
+}
+
+/**
+ * stable FNV-1a hash of the config parts that end up in the hook scripts
+ */
+fn config_hash(target_hooks: &[String], launcher: &str, chain: HookChain) -> String {
+  let mut hooks = target_hooks.to_vec();
+  hooks.sort();
+  let input = format!("{}|{}|{:?}", hooks.join(","), launcher, chain);
+  let mut hash: u64 = 0xcbf29ce484222325;
+  for byte in input.bytes() {
+    hash ^= byte as u64;
+    hash = hash.wrapping_mul(0x100000001b3);
+  }
+  format!("{:016x}", hash)
+}
+
+/**
+ * hook scripts `install` would write for the config
+ */
+struct InstallPlan {
+  hooks_dir: PathBuf,
+  scripts: Vec<(String, String)>,
+}
+
+impl InstallPlan {
+  fn new(config: &IgitConfig) -> Result<Self, Box<dyn Error>> {
+    let target_hooks = target_hooks(config);
+    let chain = config.hooks.chain;
+    let launcher = match &config.hooks.launcher {
+      Some(launcher) => launcher.to_string(),
+      None => detect_launcher(&git::root()?, std::env::current_exe().ok().as_deref()),
+    };
+    let hash = config_hash(&target_hooks, &launcher, chain);
+    let hooks_dir = git::hooks_dir()?;
+    let scripts = target_hooks
+      .iter()
+      .map(|hook_name| {
+        let backup = chained_backup(&hooks_dir.join(hook_name));
+        let backup = backup.file_name().unwrap_or_default().to_string_lossy();
+        let script = hook_script(&launcher, hook_name, &backup, chain, &hash);
+        (hook_name.to_string(), script)
+      })
+      .collect();
+    Ok(Self { hooks_dir, scripts })
+  }
+
+  fn is_target(&self, hook_name: &str) -> bool {
+    self.scripts.iter().any(|(name, _)| name == hook_name)
+  }
+
+  /**
+   * compare the planned scripts with the hooks dir
+   */
+  fn status(&self) -> Result<HooksStatus, Box<dyn Error>> {
+    let mut status = HooksStatus {
+      in_sync: true,
+      ..Default::default()
+    };
+    for (hook_name, script) in self.scripts.iter() {
+      let hook_path = self.hooks_dir.join(hook_name);
+      if !hook_path.exists() {
+        status.missing.push(hook_name.to_string());
+      } else if !is_igit_hook(&hook_path) {
+        status.foreign.push(hook_name.to_string());
+      } else if fs::read_to_string(&hook_path).ok().as_ref() != Some(script) {
+        status.outdated.push(hook_name.to_string());
+      }
+    }
+    if self.hooks_dir.exists() {
+      for path in hook_files(&self.hooks_dir)? {
+        let hook_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if !self.is_target(&hook_name) && is_igit_hook(&path) {
+          status.unused.push(hook_name);
+        }
+      }
+    }
+    status.in_sync = status.missing.is_empty() && status.outdated.is_empty() && status.unused.is_empty();
+    Ok(status)
+  }
+
+  /**
+   * write the planned scripts when they drifted, hooks of other tools do not count as drift
+   */
+  fn sync(&self) -> Result<Vec<String>, Box<dyn Error>> {
+    if self.status()?.in_sync {
+      return Ok(vec![]);
+    }
+    self.apply(false)
+  }
+
+  /**
+   * write the planned scripts, returns the names of the changed hooks.
+   * hooks written by other tools are only backed up and replaced with `replace_foreign`
+   */
+  fn apply(&self, replace_foreign: bool) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut changed = vec![];
+    if !self.hooks_dir.exists() {
+      fs::create_dir_all(&self.hooks_dir)?;
+    }
+
+    // remove igit hooks that are no longer configured, foreign hooks are left alone
+    for path in hook_files(&self.hooks_dir)? {
+      let hook_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+      if !self.is_target(&hook_name) && is_igit_hook(&path) {
+        fs::remove_file(&path)?;
+        print_change("Removed", &hook_name, "");
+        changed.push(hook_name);
+      }
+    }
+
+    // generate hooks
+    for (hook_name, hook_content) in self.scripts.iter() {
+      let hook_path = self.hooks_dir.join(hook_name);
+      if hook_path.exists() {
+        if fs::read_to_string(&hook_path).is_ok_and(|content| &content == hook_content) {
+          continue;
+        }
+        if is_igit_hook(&hook_path) {
+          print_change("Updated", hook_name, "");
+        } else if !replace_foreign {
+          continue;
+        } else {
+          let backup = backup_path(&hook_path);
+          fs::rename(&hook_path, &backup)?;
+          print_change(
+            "Backed up",
+            hook_name,
+            &format!(" \x1b[90mto\x1b[0m {}", backup.display()),
+          );
+        }
+      } else {
+        print_change("Created", hook_name, "");
+      }
+      write_hook(&hook_path, hook_content)?;
+      changed.push(hook_name.to_string());
+    }
+    Ok(changed)
+  }
+}
+
+#[derive(Debug, Default)]
+#[napi(object)]
+pub struct HooksStatus {
+  /// whether the installed hooks match the config, hooks of other tools are not counted
+  pub in_sync: bool,
+  /// configured hooks that are not installed
+  pub missing: Vec<String>,
+  /// configured hooks written by another tool, `igit install` backs them up and replaces them
+  pub foreign: Vec<String>,
+  /// igit hooks installed from an older config
+  pub outdated: Vec<String>,
+  /// igit hooks that are no longer configured
+  pub unused: Vec<String>,
+}
+
+pub fn install() -> Result<(), Box<dyn Error>> {
+  let config = config::check()?;
+  InstallPlan::new(&config)?.apply(true)?;
+  println!("{}Hooks installed", LOG_PREFIX);
+  Ok(())
+}
+
+/**
+ * compare the installed hooks with the ones `install` would generate
+ */
+pub fn status() -> Result<HooksStatus, Box<dyn Error>> {
+  let config = config::check()?;
+  InstallPlan::new(&config)?.status()
+}
+
+/**
+ * reinstall the hooks when they drifted from the config, called before running a hook.
+ * hooks written by other tools are left in place as a commit may be running them
+ */
+pub fn sync() -> Result<Vec<String>, Box<dyn Error>> {
+  let config = config::check()?;
+  if !config.hooks.auto_sync {
+    return Ok(vec![]);
+  }
+  let changed = InstallPlan::new(&config)?.sync()?;
+  if changed.is_empty() {
+    return Ok(changed);
+  }
+  println!("{}Hooks synced with the config", LOG_PREFIX);
+  Ok(changed)
+}
+
+#[derive(Debug, Default)]
+#[napi(object)]
+pub struct UninstallReport {
+  /// igit hooks that were deleted
+  pub removed: Vec<String>,
+  /// backed up hooks moved back in place
+  pub restored: Vec<String>,
+  /// older backups left in the hooks dir, only the most recent backup of a hook is restored
+  pub kept_backups: Vec<String>,
+}
+
+/**
+ * hook names that have a backup in the hooks dir
+ */
+fn backed_up_hooks(hooks_dir: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+  let mut names = vec![];
+  for entry in fs::read_dir(hooks_dir)? {
+    let file_name = entry?.file_name().to_string_lossy().to_string();
+    if let Some((name, _)) = parse_backup_name(&file_name) {
+      names.push(name.to_string());
+    }
+  }
+  names.sort();
+  names.dedup();
+  Ok(names)
+}
+
+/**
+ * remove igit hooks and restore the hooks they replaced, foreign hooks are left alone
+ */
+pub fn uninstall() -> Result<UninstallReport, Box<dyn Error>> {
+  let hooks_dir = git::hooks_dir()?;
+  if !hooks_dir.exists() {
+    println!("{}No hooks to uninstall", LOG_PREFIX);
+    return Ok(UninstallReport::default());
+  }
+  let report = uninstall_from(&hooks_dir)?;
+  println!("{}Hooks uninstalled", LOG_PREFIX);
+  Ok(report)
+}
+
+fn uninstall_from(hooks_dir: &Path) -> Result<UninstallReport, Box<dyn Error>> {
+  let mut report = UninstallReport::default();
+  for path in hook_files(hooks_dir)? {
+    if is_igit_hook(&path) {
+      let hook_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+      fs::remove_file(&path)?;
+      print_change("Removed", &hook_name, "");
+      report.removed.push(hook_name);
+    }
+  }
+
+  for hook_name in backed_up_hooks(hooks_dir)? {
+    let hook_path = hooks_dir.join(&hook_name);
+    if hook_path.exists() {
+      continue;
+    }
+    if let Some(backup) = latest_backup(&hook_path) {
+      fs::rename(&backup, &hook_path)?;
+      print_change(
+        "Restored",
+        &hook_name,
+        &format!(" \x1b[90mfrom\x1b[0m {}", backup.display()),
+      );
+      report.restored.push(hook_name.to_string());
+    }
+    // hooks replaced again after an earlier backup, they may still be wanted so they are only listed
+    let kept = backup_indexes(&hook_path)
+      .into_iter()
+      .map(|index| backup_file_name(&hook_name, index))
+      .collect::<Vec<String>>();
+    if !kept.is_empty() {
+      println!(
+        "{}\x1b[33mKept the older backups {} in {}, remove them once they are not needed\x1b[0m",
+        LOG_PREFIX,
+        kept.join(", "),
+        hooks_dir.display()
+      );
+      report.kept_backups.extend(kept);
+    }
+  }
+  Ok(report)
+}
+
+static LOCKFILE_LAUNCHERS: &[(&str, &str)] = &[
+  ("pnpm-lock.yaml", "pnpm exec igit"),
+  ("yarn.lock", "yarn igit"),
+  ("bun.lock", "bunx igit"),
+  ("bun.lockb", "bunx igit"),
+  ("package-lock.json", "npx --no-install igit"),
+];
+
+/// runtimes the npm package runs in, any other executable is a standalone igit binary
+static JS_RUNTIMES: &[&str] = &["node", "nodejs", "bun", "deno"];
+
+/**
+ * pick the launcher of the package manager used by the repository, a standalone
+ * igit binary launches itself
+ */
+fn detect_launcher(root: &Path, current_exe: Option<&Path>) -> String {
+  let standalone = current_exe.filter(|exe| {
+    let name = exe.file_stem().unwrap_or_default().to_string_lossy().to_lowercase();
+    !JS_RUNTIMES.contains(&name.as_str())
+  });
+  if let Some(exe) = standalone {
+    return quote_arg(&exe.to_string_lossy());
+  }
+  LOCKFILE_LAUNCHERS
+    .iter()
+    .find(|(lockfile, _)| root.join(lockfile).exists())
+    .map(|(_, launcher)| launcher.to_string())
+    .unwrap_or_else(|| "npx igit".to_string())
+}
+
+/**
+ * split the launcher into its program and the rest of the command line,
+ * a program wrapped in quotes may contain spaces
+ */
+fn split_launcher(launcher: &str) -> (&str, &str) {
+  let launcher = launcher.trim();
+  if let Some(quote) = launcher.chars().next().filter(|c| *c == '\'' || *c == '"') {
+    if let Some(end) = launcher[1..].find(quote) {
+      return (&launcher[1..end + 1], launcher[end + 2..].trim_start());
+    }
+  }
+  match launcher.split_once(char::is_whitespace) {
+    Some((program, rest)) => (program, rest.trim_start()),
+    None => (launcher, ""),
+  }
+}
+
+/// hooks git feeds on stdin, the input is kept so every chained hook can read it
+static STDIN_HOOKS: &[&str] = &[
+  "pre-push",
+  "pre-receive",
+  "post-receive",
+  "post-rewrite",
+  "reference-transaction",
+];
+
+/**
+ * shell lines running `<hook>.local` and the backed up hook, stops with their exit code on failure
+ */
+fn chain_script(hook_name: &str, backup: &str, reads_stdin: bool) -> String {
+  let input = if reads_stdin { "igit_input | " } else { "" };
+  format!(
+    r#"for chained in "$hook_dir/{hook_name}.local" "$hook_dir/{backup}"; do
+  if [ -x "$chained" ]; then
+    {input}"$chained" "$@" || exit $?
+  fi
+done
+"#,
+    hook_name = hook_name,
+    backup = backup,
+    input = input
+  )
+}
+
+/**
+ * content of an installed hook, fails with a clear message if the launcher is missing
+ */
+fn hook_script(launcher: &str, hook_name: &str, backup: &str, chain: HookChain, hash: &str) -> String {
+  let (program, args) = split_launcher(launcher);
+  let quoted_program = quote_arg(program);
+  let launcher = match args {
+    "" => quoted_program.to_string(),
+    args => format!("{} {}", quoted_program, args),
+  };
+  // the program is shown inside a double quoted string
+  let program_name = program.replace('\\', "\\\\").replace('"', "\\\"").replace('$', "\\$").replace('`', "\\`");
+  let reads_stdin = chain != HookChain::Off && STDIN_HOOKS.contains(&hook_name);
+  let mut script = format!(
+    r#"#!/usr/bin/env sh
+{marker}
+# igit: config {hash}
+if ! command -v {program} >/dev/null 2>&1; then
+  echo "[iGit] Can not find \`{program_name}\` to run the {hook_name} hook, install it or set \`hooks.launcher\` in the igit config." >&2
+  exit 127
+fi
+"#,
+    marker = HOOK_MARKER,
+    hash = hash,
+    program = quoted_program,
+    program_name = program_name,
+    hook_name = hook_name,
+  );
+  if chain != HookChain::Off {
+    script.push_str("hook_dir=$(dirname \"$0\")\n");
+  }
+  if reads_stdin {
+    script.push_str("igit_stdin=$(cat)\n");
+    script.push_str("igit_input() {\n  if [ -n \"$igit_stdin\" ]; then printf '%s\\n' \"$igit_stdin\"; fi\n}\n");
+  }
+  let input = if reads_stdin { "igit_input | " } else { "" };
+  let run = format!("{}{} run \"{}\" \"$@\"", input, launcher, hook_name);
+  match chain {
+    HookChain::Before => {
+      script.push_str(&chain_script(hook_name, backup, reads_stdin));
+      script.push_str(&run);
+      script.push('\n');
+    }
+    HookChain::After => {
+      script.push_str(&run);
+      script.push_str(" || exit $?\n");
+      script.push_str(&chain_script(hook_name, backup, reads_stdin));
+    }
+    HookChain::Off => {
+      script.push_str(&run);
+      script.push('\n');
+    }
+  }
+  script
+}
+
+/* ------------ test ------------ */
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_detect_launcher() {
+    let dir = std::env::temp_dir().join("igit_detect_launcher");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let node = Path::new("/usr/bin/node");
+    assert_eq!(detect_launcher(&dir, Some(node)), "npx igit");
+    fs::write(dir.join("package-lock.json"), "{}").unwrap();
+    assert_eq!(detect_launcher(&dir, Some(node)), "npx --no-install igit");
+    fs::write(dir.join("pnpm-lock.yaml"), "").unwrap();
+    assert_eq!(detect_launcher(&dir, Some(node)), "pnpm exec igit");
+    assert_eq!(detect_launcher(&dir, None), "pnpm exec igit");
+    assert_eq!(detect_launcher(&dir, Some(Path::new("/opt/my tools/igit"))), "'/opt/my tools/igit'");
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_hook_script() {
+    let script = hook_script("pnpm exec igit", "pre-commit", "pre-commit.igit-backup", HookChain::Off, "0");
+    assert!(script.starts_with("#!/usr/bin/env sh\n"));
+    assert!(script.contains("if ! command -v pnpm >/dev/null 2>&1; then"));
+    assert!(script.ends_with("fi\npnpm exec igit run \"pre-commit\" \"$@\"\n"));
+
+    let script = hook_script("pnpm exec igit", "pre-commit", "pre-commit.igit-backup", HookChain::Before, "0");
+    assert!(script.contains("\"$hook_dir/pre-commit.local\" \"$hook_dir/pre-commit.igit-backup\""));
+    assert!(!script.contains("igit_stdin"));
+    assert!(script.ends_with("done\npnpm exec igit run \"pre-commit\" \"$@\"\n"));
+
+    let script = hook_script("npx igit", "pre-push", "pre-push.igit-backup", HookChain::After, "0");
+    assert!(script.contains("igit_stdin=$(cat)"));
+    assert!(script.contains("igit_input | npx igit run \"pre-push\" \"$@\" || exit $?\n"));
+    assert!(script.contains("    igit_input | \"$chained\" \"$@\" || exit $?\n"));
+    assert!(script.ends_with("done\n"));
+
+    let script = hook_script("'/opt/my tools/igit'", "pre-commit", "pre-commit.igit-backup", HookChain::Off, "0");
+    assert!(script.contains("if ! command -v '/opt/my tools/igit' >/dev/null 2>&1; then"));
+    assert!(script.contains("Can not find \\`/opt/my tools/igit\\` to run"));
+    assert!(script.ends_with("fi\n'/opt/my tools/igit' run \"pre-commit\" \"$@\"\n"));
+  }
+
+  #[test]
+  fn test_split_launcher() {
+    assert_eq!(split_launcher("pnpm exec igit"), ("pnpm", "exec igit"));
+    assert_eq!(split_launcher("igit"), ("igit", ""));
+    assert_eq!(split_launcher("\"/opt/my tools/igit\" --quiet"), ("/opt/my tools/igit", "--quiet"));
+    assert_eq!(split_launcher("'/opt/my tools/igit'"), ("/opt/my tools/igit", ""));
+  }
+
+  #[test]
+  fn test_is_igit_hook_and_backup_path() {
+    let dir = std::env::temp_dir().join("igit_is_igit_hook");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let igit_hook = dir.join("pre-commit");
+    let foreign_hook = dir.join("pre-push");
+    fs::write(&igit_hook, hook_script("npx igit", "pre-commit", "pre-commit.igit-backup", HookChain::Before, "0")).unwrap();
+    fs::write(&foreign_hook, "#!/bin/sh\ngit lfs pre-push \"$@\"\n").unwrap();
+    assert!(is_igit_hook(&igit_hook));
+    assert!(!is_igit_hook(&foreign_hook));
+    let legacy_hook = dir.join("commit-msg");
+    fs::write(&legacy_hook, "#!/usr/bin/env sh\nnpx igit run \"commit-msg\" \"$@\"\n").unwrap();
+    assert!(is_igit_hook(&legacy_hook));
+    fs::write(&legacy_hook, "#!/usr/bin/env sh\nnpx igit run \"pre-push\" \"$@\"\n").unwrap();
+    assert!(!is_igit_hook(&legacy_hook));
+    fs::write(&legacy_hook, "#!/usr/bin/env sh\nnpx igit run \"commit-msg\" \"$@\"\nnpm test\n").unwrap();
+    assert!(!is_igit_hook(&legacy_hook));
+    fs::remove_file(&legacy_hook).unwrap();
+
+    assert_eq!(backup_path(&foreign_hook), dir.join("pre-push.igit-backup"));
+    fs::write(dir.join("pre-push.igit-backup"), "").unwrap();
+    assert_eq!(
+      backup_path(&foreign_hook),
+      dir.join("pre-push.igit-backup.1")
+    );
+    assert_eq!(
+      hook_files(&dir).unwrap(),
+      vec![igit_hook.clone(), foreign_hook.clone()]
+    );
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_apply_replaces_legacy_hooks_without_backup() {
+    let dir = std::env::temp_dir().join("igit_apply_legacy_hooks");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("pre-commit"), "#!/usr/bin/env sh\nnpx igit run \"pre-commit\" \"$@\"\n").unwrap();
+    fs::write(dir.join("pre-push"), "#!/usr/bin/env sh\nnpx igit run \"pre-push\" \"$@\"\n").unwrap();
+    let script = hook_script("npx igit", "pre-commit", "pre-commit.igit-backup", HookChain::Before, "0");
+    let plan = InstallPlan {
+      hooks_dir: dir.clone(),
+      scripts: vec![("pre-commit".to_string(), script.clone())],
+    };
+    assert_eq!(plan.status().unwrap().outdated, vec!["pre-commit".to_string()]);
+    assert_eq!(plan.apply(true).unwrap(), vec!["pre-push".to_string(), "pre-commit".to_string()]);
+    assert_eq!(fs::read_to_string(dir.join("pre-commit")).unwrap(), script);
+    assert!(!dir.join("pre-push").exists());
+    assert!(backed_up_hooks(&dir).unwrap().is_empty());
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_apply_keeps_foreign_hooks_when_syncing() {
+    let dir = std::env::temp_dir().join("igit_apply_sync");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let foreign = "#!/bin/sh\ngit lfs pre-push \"$@\"\n";
+    fs::write(dir.join("pre-push"), foreign).unwrap();
+    fs::write(dir.join("pre-commit"), hook_script("npx igit", "pre-commit", "pre-commit.igit-backup", HookChain::Off, "0")).unwrap();
+    let scripts = ["pre-commit", "pre-push"]
+      .iter()
+      .map(|hook_name| {
+        let backup = format!("{}.igit-backup", hook_name);
+        (hook_name.to_string(), hook_script("npx igit", hook_name, &backup, HookChain::Before, "1"))
+      })
+      .collect::<Vec<(String, String)>>();
+    let plan = InstallPlan {
+      hooks_dir: dir.clone(),
+      scripts: scripts.clone(),
+    };
+    assert_eq!(plan.apply(false).unwrap(), vec!["pre-commit".to_string()]);
+    assert_eq!(fs::read_to_string(dir.join("pre-commit")).unwrap(), scripts[0].1);
+    assert_eq!(fs::read_to_string(dir.join("pre-push")).unwrap(), foreign);
+    assert!(backed_up_hooks(&dir).unwrap().is_empty());
+    assert_eq!(hook_files(&dir).unwrap().len(), 2);
+
+    assert_eq!(plan.apply(true).unwrap(), vec!["pre-push".to_string()]);
+    assert_eq!(fs::read_to_string(dir.join("pre-push.igit-backup")).unwrap(), foreign);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_sync_leaves_foreign_hooks_alone() {
+    let dir = std::env::temp_dir().join("igit_sync_foreign");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let foreign = "#!/bin/sh\ngit lfs pre-push \"$@\"\n";
+    fs::write(dir.join("pre-push"), foreign).unwrap();
+    let scripts = ["pre-commit", "pre-push"]
+      .iter()
+      .map(|hook_name| {
+        let backup = format!("{}.igit-backup", hook_name);
+        (hook_name.to_string(), hook_script("npx igit", hook_name, &backup, HookChain::Before, "1"))
+      })
+      .collect::<Vec<(String, String)>>();
+    let plan = InstallPlan {
+      hooks_dir: dir.clone(),
+      scripts,
+    };
+    let status = plan.status().unwrap();
+    assert_eq!((status.in_sync, status.missing, status.foreign), (false, vec!["pre-commit".to_string()], vec!["pre-push".to_string()]));
+    assert_eq!(plan.sync().unwrap(), vec!["pre-commit".to_string()]);
+
+    // the foreign hook is a stable state, nothing is written or printed again
+    let status = plan.status().unwrap();
+    assert!(status.in_sync);
+    assert_eq!(status.foreign, vec!["pre-push".to_string()]);
+    let modified = fs::metadata(dir.join("pre-commit")).unwrap().modified().unwrap();
+    assert!(plan.sync().unwrap().is_empty());
+    assert_eq!(fs::metadata(dir.join("pre-commit")).unwrap().modified().unwrap(), modified);
+    assert_eq!(fs::read_to_string(dir.join("pre-push")).unwrap(), foreign);
+    assert_eq!(hook_files(&dir).unwrap().len(), 2);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_uninstall_restores_the_latest_backup() {
+    let dir = std::env::temp_dir().join("igit_uninstall_backups");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("pre-push.igit-backup"), "first").unwrap();
+    fs::write(dir.join("pre-push.igit-backup.2"), "latest").unwrap();
+    fs::write(dir.join("pre-push"), hook_script("npx igit", "pre-push", "pre-push.igit-backup.2", HookChain::Before, "0")).unwrap();
+    let report = uninstall_from(&dir).unwrap();
+    assert_eq!(report.removed, vec!["pre-push".to_string()]);
+    assert_eq!(report.restored, vec!["pre-push".to_string()]);
+    assert_eq!(report.kept_backups, vec!["pre-push.igit-backup".to_string()]);
+    assert_eq!(fs::read_to_string(dir.join("pre-push")).unwrap(), "latest");
+
+    // the restored hook is not replaced by an older backup on the next run
+    let report = uninstall_from(&dir).unwrap();
+    assert!(report.removed.is_empty() && report.restored.is_empty() && report.kept_backups.is_empty());
+    assert_eq!(fs::read_to_string(dir.join("pre-push")).unwrap(), "latest");
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_config_hash() {
+    let hooks = vec!["pre-commit".to_string(), "commit-msg".to_string()];
+    let reversed = vec!["commit-msg".to_string(), "pre-commit".to_string()];
+    let hash = config_hash(&hooks, "npx igit", HookChain::Before);
+    assert_eq!(hash.len(), 16);
+    assert_eq!(hash, config_hash(&reversed, "npx igit", HookChain::Before));
+    assert_ne!(hash, config_hash(&hooks, "pnpm exec igit", HookChain::Before));
+    assert_ne!(hash, config_hash(&hooks[..1], "npx igit", HookChain::Before));
+    let script = hook_script("npx igit", "pre-commit", "pre-commit.igit-backup", HookChain::Before, &hash);
+    assert!(script.contains(&format!("\n# igit: config {}\n", hash)));
+  }
+
+  #[test]
+  fn test_latest_backup() {
+    let dir = std::env::temp_dir().join("igit_latest_backup");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let hook = dir.join("pre-push");
+    assert_eq!(latest_backup(&hook), None);
+    fs::write(dir.join("pre-push.igit-backup"), "").unwrap();
+    assert_eq!(latest_backup(&hook), Some(dir.join("pre-push.igit-backup")));
+    fs::write(dir.join("pre-push.igit-backup.1"), "").unwrap();
+    assert_eq!(latest_backup(&hook), Some(dir.join("pre-push.igit-backup.1")));
+    // numbers are not always contiguous after backups were restored
+    fs::remove_file(dir.join("pre-push.igit-backup")).unwrap();
+    fs::write(dir.join("pre-push.igit-backup.3"), "").unwrap();
+    fs::write(dir.join("pre-push.igit-backup.x"), "").unwrap();
+    assert_eq!(latest_backup(&hook), Some(dir.join("pre-push.igit-backup.3")));
+    assert_eq!(backup_path(&hook), dir.join("pre-push.igit-backup.4"));
+    fs::write(dir.join("pre-commit.igit-backup.1"), "").unwrap();
+    assert_eq!(
+      backed_up_hooks(&dir).unwrap(),
+      vec!["pre-commit".to_string(), "pre-push".to_string()]
+    );
+
+    // the installed hook chains the backup uninstall restores
+    let foreign = "#!/bin/sh\ngit lfs pre-push \"$@\"\n";
+    fs::write(&hook, foreign).unwrap();
+    assert_eq!(chained_backup(&hook), dir.join("pre-push.igit-backup.4"));
+    fs::rename(&hook, backup_path(&hook)).unwrap();
+    fs::write(&hook, hook_script("npx igit", "pre-push", "pre-push.igit-backup.4", HookChain::Before, "0")).unwrap();
+    assert_eq!(chained_backup(&hook), dir.join("pre-push.igit-backup.4"));
+    assert_eq!(fs::read_to_string(latest_backup(&hook).unwrap()).unwrap(), foreign);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -4,27 +4,84 @@
 extern crate napi_derive;
 
 use command::ShellCommand;
+use diagnostic::ConfigIssue;
+use init::{InitOptions, InitReport, ProjectInfo};
+use install::{HooksStatus, UninstallReport};
+use migrate::MigrateReport;
 use napi::{Error, Result};
+use runner::RunReport;
 mod config;
+mod diagnostic;
+mod extends;
+mod format;
 mod git;
 mod hooks;
+mod init;
+mod install;
 mod command;
+mod commit_lint;
 mod auto_commit;
 mod log;
+mod migrate;
+mod runner;
+mod schema;
+mod staged;
+mod stash;
 
 #[napi]
-pub fn init() -> Result<()> {
-  config::init().map_err(|e| Error::from_reason(e.to_string()))
+pub fn init(options: Option<InitOptions>) -> Result<InitReport> {
+  init::init(options.unwrap_or_default()).map_err(Error::from_reason)
+}
+
+#[napi]
+pub fn detect_project() -> Result<ProjectInfo> {
+  let current_dir = std::env::current_dir().map_err(|e| Error::from_reason(e.to_string()))?;
+  Ok(init::detect(&current_dir))
+}
+
+#[napi]
+pub fn resolved_config() -> Result<String> {
+  config::resolved().map_err(Error::from_reason)
+}
+
+#[napi]
+pub fn config_schema() -> String {
+  schema::generate()
+}
+
+#[napi]
+pub fn validate_config() -> Result<Vec<ConfigIssue>> {
+  config::validate().map_err(Error::from_reason)
+}
+
+#[napi]
+pub fn migrate_config(dry_run: Option<bool>) -> Result<MigrateReport> {
+  migrate::migrate(dry_run.unwrap_or(false)).map_err(Error::from_reason)
 }
 
 #[napi]
 pub fn install() -> Result<()> {
-  hooks::install().map_err(|e| Error::from_reason(e.to_string()))
+  install::install().map_err(|e| Error::from_reason(e.to_string()))
+}
+
+#[napi]
+pub fn hooks_status() -> Result<HooksStatus> {
+  install::status().map_err(|e| Error::from_reason(e.to_string()))
+}
+
+#[napi]
+pub fn sync_hooks() -> Result<Vec<String>> {
+  install::sync().map_err(|e| Error::from_reason(e.to_string()))
+}
+
+#[napi]
+pub fn uninstall() -> Result<UninstallReport> {
+  install::uninstall().map_err(|e| Error::from_reason(e.to_string()))
 }
 
 #[napi]
 pub fn collect_staged_commands() -> Result<Vec<ShellCommand>> {
-  hooks::collect_staged_commands().map_err(|e| Error::from_reason(e.to_string()))
+  staged::collect_staged_commands().map_err(|e| Error::from_reason(e.to_string()))
 }
 
 #[napi]
@@ -32,7 +89,17 @@ pub fn collect_hook_commands(hook_name: String, args: Vec<String>) -> Result<Vec
   hooks::collect_hook_commands(&hook_name, &args).map_err(|e| Error::from_reason(e.to_string()))
 }
 
+#[napi]
+pub fn run_staged_hooks() -> Result<RunReport> {
+  staged::run_staged_commands().map_err(|e| Error::from_reason(e.to_string()))
+}
+
+#[napi]
+pub fn run_hook(hook_name: String, args: Vec<String>) -> Result<RunReport> {
+  hooks::run_hook_commands(&hook_name, &args).map_err(|e| Error::from_reason(e.to_string()))
+}
+
 #[napi]
 pub async fn auto_commit() -> Result<String> {
-  auto_commit::generate_commit_message().await.map_err(|e| Error::from_reason(e))
+  auto_commit::generate_commit_message().await.map_err(Error::from_reason)
 }
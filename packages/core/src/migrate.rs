@@ -0,0 +1,185 @@
+use crate::config;
+use crate::diagnostic;
+use crate::format::{self, Format};
+use crate::log::LOG_PREFIX;
+use crate::schema::{self, SCHEMA_VERSION};
+use napi_derive::napi;
+use std::fs;
+use std::path::Path;
+
+static SCHEMA_PATH: &str = "/schema/";
+
+#[napi(object)]
+pub struct MigrateReport {
+  pub config_file: String,
+  /// schema version the config referenced, `undefined` when it did not reference one
+  pub from_version: Option<String>,
+  pub to_version: String,
+  /// what was rewritten, empty when the config is up to date
+  pub changes: Vec<String>,
+  /// false for dry runs and configs that are up to date
+  pub written: bool,
+}
+
+/**
+ * schema version of the url on a line, like `0.0.2` in `https://igit.erguotou.me/schema/0.0.2/schema.json`
+ */
+fn url_version(line: &str) -> Option<&str> {
+  let start = line.find(SCHEMA_PATH)? + SCHEMA_PATH.len();
+  let version = line[start..].split('/').next()?;
+  (!version.is_empty() && version.chars().all(|c| c.is_ascii_digit() || c == '.')).then_some(version)
+}
+
+/**
+ * index of the line referencing the schema: the yaml-language-server comment of yaml,
+ * the `#:schema` directive of toml or the `$schema` key of json
+ */
+fn schema_line(lines: &[String]) -> Option<usize> {
+  lines.iter().position(|line| {
+    let line = line.trim_start();
+    (line.starts_with("# yaml-language-server:") || line.starts_with("#:schema") || line.starts_with("\"$schema\""))
+      && url_version(line).is_some()
+  })
+}
+
+/**
+ * detect the schema version of a config file
+ */
+pub fn detect_version(content: &str) -> Option<String> {
+  let lines = content.lines().map(str::to_string).collect::<Vec<String>>();
+  schema_line(&lines).and_then(|index| url_version(&lines[index]).map(str::to_string))
+}
+
+/**
+ * the migrated content with the list of changes, only the schema reference is rewritten
+ * as every published schema version reads the keys of the older ones, unknown keys are
+ * left to the suggestions of `igit config --check`
+ */
+fn migrate_content(path: &Path, content: &str) -> Result<(String, Vec<String>), String> {
+  let (_, warnings) = diagnostic::check(path, content).map_err(|e| format!("Fix the config first: {}", e))?;
+  diagnostic::warn(&warnings);
+  let mut lines = content.lines().map(str::to_string).collect::<Vec<String>>();
+  let mut changes = vec![];
+
+  let url = schema::url();
+  match schema_line(&lines) {
+    Some(index) => {
+      let version = url_version(&lines[index]).unwrap_or_default().to_string();
+      if version != SCHEMA_VERSION {
+        let start = lines[index].find("http").unwrap_or(0);
+        let end = lines[index].find("schema.json").map(|end| end + "schema.json".len()).unwrap_or(lines[index].len());
+        lines[index].replace_range(start..end, &url);
+        changes.push(format!("Updated the schema from {} to {}", version, SCHEMA_VERSION));
+      }
+    }
+    None if Format::of(path) == Format::Yaml && format::embedded_keys(path).is_empty() => {
+      lines.insert(0, format!("# yaml-language-server: $schema={}", url));
+      changes.push(format!("Added the schema {}", SCHEMA_VERSION));
+    }
+    None => {}
+  }
+
+  let mut migrated = lines.join("\n");
+  if content.ends_with('\n') {
+    migrated.push('\n');
+  }
+  diagnostic::check(path, &migrated).map_err(|e| format!("Failed to migrate the config: {}", e))?;
+  Ok((migrated, changes))
+}
+
+/**
+ * migrate the nearest config to the current schema version
+ */
+pub fn migrate(dry_run: bool) -> Result<MigrateReport, String> {
+  let current_dir = std::env::current_dir().map_err(|e| format!("Failed to get current directory: {}", e))?;
+  let path = config::find_repo_config_file_from(&current_dir)
+    .ok_or("Failed to find config file, please use `igit init` to create one.")?;
+  let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read config {}: {}", path.display(), e))?;
+  let (migrated, changes) = migrate_content(&path, &content)?;
+  let config_file = path.strip_prefix(&current_dir).unwrap_or(&path).display().to_string();
+  let report = MigrateReport {
+    config_file,
+    from_version: detect_version(&content),
+    to_version: SCHEMA_VERSION.to_string(),
+    written: !dry_run && !changes.is_empty(),
+    changes,
+  };
+
+  if report.changes.is_empty() {
+    println!("{}Config {} is up to date", LOG_PREFIX, report.config_file);
+    return Ok(report);
+  }
+  if report.written {
+    fs::write(&path, migrated).map_err(|e| format!("Failed to write config {}: {}", path.display(), e))?;
+  }
+  println!(
+    "{}{} \x1b[34m{}\x1b[0m to {}",
+    LOG_PREFIX,
+    if dry_run { "Would migrate" } else { "Migrated" },
+    report.config_file,
+    SCHEMA_VERSION
+  );
+  for change in &report.changes {
+    println!("{}  \x1b[90m-\x1b[0m {}", LOG_PREFIX, change);
+  }
+  Ok(report)
+}
+
+/* ------------ test ------------ */
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_migrate_yaml() {
+    let content = "# yaml-language-server: $schema=https://igit.erguotou.me/schema/0.0.1/schema.json
+# lint staged files
+staged_hooks:
+  enabled: true # on every commit
+  rules:
+    '*.ts': eslint
+commit_msg:
+  enabled: true
+  prependEmoji: true
+";
+    assert_eq!(detect_version(content).as_deref(), Some("0.0.1"));
+    let (migrated, changes) = migrate_content(Path::new("igit.yaml"), content).unwrap();
+    assert_eq!(migrated, content.replacen("https://igit.erguotou.me/schema/0.0.1/schema.json", &schema::url(), 1));
+    assert_eq!(changes, vec![format!("Updated the schema from 0.0.1 to {}", SCHEMA_VERSION)]);
+    let (_, changes) = migrate_content(Path::new("igit.yaml"), &migrated).unwrap();
+    assert!(changes.is_empty());
+  }
+
+  #[test]
+  fn test_migrate_keeps_keys() {
+    // unknown keys are only reported, `igit config --check` suggests the known ones
+    let content = "# yaml-language-server: $schema=https://igit.erguotou.me/schema/0.0.2/schema.json\nstagedHooks:\n  enabled: true\ncommit_msg:\n  foo_bar: true\n";
+    let (migrated, changes) = migrate_content(Path::new("igit.yaml"), content).unwrap();
+    assert!(migrated.ends_with("\nstagedHooks:\n  enabled: true\ncommit_msg:\n  foo_bar: true\n"));
+    assert_eq!(changes.len(), 1);
+  }
+
+  #[test]
+  fn test_migrate_other_formats() {
+    let (migrated, changes) = migrate_content(Path::new("igit.yaml"), "hooks:\n  enabled: true\n").unwrap();
+    assert!(migrated.starts_with("# yaml-language-server: $schema="));
+    assert!(migrated.ends_with("\nhooks:\n  enabled: true\n"));
+    assert_eq!(changes, vec![format!("Added the schema {}", SCHEMA_VERSION)]);
+
+    let content = "#:schema https://igit.erguotou.me/schema/0.0.2/schema.json\n[commit_msg]\nenabled = true\n";
+    let (migrated, changes) = migrate_content(Path::new("igit.toml"), content).unwrap();
+    assert_eq!(migrated, format!("#:schema {}\n[commit_msg]\nenabled = true\n", schema::url()));
+    assert_eq!(changes.len(), 1);
+
+    let content = "{\n  \"$schema\": \"https://igit.erguotou.me/schema/0.0.3/schema.json\",\n  \"hooks\": {}\n}\n";
+    let (migrated, changes) = migrate_content(Path::new("igit.json"), content).unwrap();
+    assert!(migrated.contains(&format!("\"$schema\": \"{}\",", schema::url())));
+    assert_eq!(changes.len(), 1);
+
+    // configs embedded in package.json have no schema reference to update
+    let content = "{\n  \"igit\": {\n    \"hooks\": {\n      \"autoSync\": false\n    }\n  }\n}\n";
+    let (migrated, changes) = migrate_content(Path::new("package.json"), content).unwrap();
+    assert_eq!(migrated, content);
+    assert!(changes.is_empty());
+  }
+}
@@ -0,0 +1,256 @@
+use crate::command::ShellCommand;
+use std::io::{IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Instant;
+
+#[derive(Debug)]
+#[napi(object)]
+pub struct CommandResult {
+  pub command: String,
+  #[napi(ts_type = "string[]")]
+  pub args: Option<Vec<String>>,
+  /// `undefined` when the process was killed by a signal or failed to spawn
+  pub exit_code: Option<i32>,
+  pub success: bool,
+  pub stdout: String,
+  pub stderr: String,
+  pub duration_ms: u32,
+}
+
+#[derive(Debug)]
+#[napi(object)]
+pub struct RunReport {
+  pub success: bool,
+  pub results: Vec<CommandResult>,
+  /// wall-clock time of the whole run, commands running in parallel overlap
+  pub duration_ms: u32,
+}
+
+impl Default for RunReport {
+  fn default() -> Self {
+    Self {
+      success: true,
+      results: vec![],
+      duration_ms: 0,
+    }
+  }
+}
+
+impl RunReport {
+  pub fn push(&mut self, result: CommandResult) {
+    self.success = self.success && result.success;
+    self.results.push(result);
+  }
+
+  pub fn merge(&mut self, other: RunReport) {
+    for result in other.results {
+      self.push(result);
+    }
+  }
+
+  /**
+   * first failed command of the report
+   */
+  pub fn first_failure(&self) -> Option<&CommandResult> {
+    self.results.iter().find(|r| !r.success)
+  }
+}
+
+/**
+ * where the output of a command goes
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+  /// to the terminal as it comes and into the result
+  Stream,
+  /// into the result, for commands whose output is printed later
+  Capture,
+}
+
+fn shell() -> Command {
+  if cfg!(windows) {
+    let mut command = Command::new("cmd");
+    command.arg("/C");
+    command
+  } else {
+    let mut command = Command::new("sh");
+    command.arg("-c");
+    command
+  }
+}
+
+/**
+ * copy a pipe to the terminal as it is read, returns what was read
+ */
+fn tee(mut pipe: impl Read, mut terminal: impl Write) -> Vec<u8> {
+  let mut copy = vec![];
+  let mut buffer = [0; 8192];
+  loop {
+    match pipe.read(&mut buffer) {
+      Ok(0) => break,
+      Ok(read) => {
+        let _ = terminal.write_all(&buffer[..read]);
+        let _ = terminal.flush();
+        copy.extend_from_slice(&buffer[..read]);
+      }
+      Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+      Err(_) => break,
+    }
+  }
+  copy
+}
+
+/**
+ * run the command printing its output as it comes, tools writing to the pipe are told
+ * to keep their colors when igit prints to a terminal
+ */
+fn stream(mut command: Command) -> std::io::Result<std::process::Output> {
+  if std::io::stdout().is_terminal() && std::env::var_os("FORCE_COLOR").is_none() {
+    command.env("FORCE_COLOR", "1");
+  }
+  let mut child = command.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+  let stdout = child.stdout.take().map(|pipe| thread::spawn(move || tee(pipe, std::io::stdout())));
+  let stderr = child.stderr.take().map(|pipe| thread::spawn(move || tee(pipe, std::io::stderr())));
+  let status = child.wait()?;
+  let join = |copy: Option<thread::JoinHandle<Vec<u8>>>| copy.and_then(|copy| copy.join().ok()).unwrap_or_default();
+  Ok(std::process::Output {
+    status,
+    stdout: join(stdout),
+    stderr: join(stderr),
+  })
+}
+
+pub fn elapsed_ms(start: Instant) -> u32 {
+  u32::try_from(start.elapsed().as_millis()).unwrap_or(u32::MAX)
+}
+
+/**
+ * run a single command through the platform shell
+ */
+pub fn run_command(shell_command: &ShellCommand, output: Output) -> CommandResult {
+  let start = Instant::now();
+  let mut command = shell();
+  command.arg(shell_command.to_command_line()).stdin(Stdio::inherit());
+  if let Some(cwd) = &shell_command.cwd {
+    command.current_dir(cwd);
+  }
+  let mode = output;
+  let output = match mode {
+    Output::Capture => command.output(),
+    Output::Stream => stream(command),
+  };
+  match output {
+    Ok(output) => CommandResult {
+      command: shell_command.command.clone(),
+      args: shell_command.args.clone(),
+      exit_code: output.status.code(),
+      success: output.status.success(),
+      stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+      stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+      duration_ms: elapsed_ms(start),
+    },
+    Err(e) => {
+      let stderr = format!("Failed to spawn `{}`: {}\n", shell_command.command, e);
+      if mode == Output::Stream {
+        eprint!("{}", stderr);
+      }
+      CommandResult {
+        command: shell_command.command.clone(),
+        args: shell_command.args.clone(),
+        exit_code: None,
+        success: false,
+        stdout: String::new(),
+        stderr,
+        duration_ms: elapsed_ms(start),
+      }
+    }
+  }
+}
+
+/**
+ * echo the captured output of a command
+ */
+pub fn print_result(result: &CommandResult) {
+  if !result.stdout.is_empty() {
+    print!("{}", result.stdout);
+    let _ = std::io::stdout().flush();
+  }
+  if !result.stderr.is_empty() {
+    eprint!("{}", result.stderr);
+  }
+}
+
+/**
+ * run commands one after another streaming their output, stop at the first failure.
+ * `before` is called right before a command is spawned
+ */
+pub fn run_commands(commands: &[ShellCommand], before: impl Fn(&ShellCommand)) -> RunReport {
+  let start = Instant::now();
+  let mut report = RunReport::default();
+  for command in commands {
+    before(command);
+    let result = run_command(command, Output::Stream);
+    let success = result.success;
+    report.push(result);
+    if !success {
+      break;
+    }
+  }
+  report.duration_ms = elapsed_ms(start);
+  report
+}
+
+/* ------------ test ------------ */
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_run_command_captures_output() {
+    let result = run_command(&ShellCommand::with_args("echo", vec!["hello world"]), Output::Capture);
+    assert!(result.success);
+    assert_eq!(result.exit_code, Some(0));
+    assert_eq!(result.stdout.trim(), "hello world");
+  }
+
+  #[test]
+  fn test_run_command_in_cwd() {
+    let dir = std::env::temp_dir();
+    let mut command = ShellCommand::new("pwd");
+    command.cwd = Some(dir.to_string_lossy().to_string());
+    let result = run_command(&command, Output::Capture);
+    assert!(result.success);
+    assert_eq!(
+      std::fs::canonicalize(result.stdout.trim()).unwrap(),
+      std::fs::canonicalize(&dir).unwrap()
+    );
+  }
+
+  #[test]
+  fn test_run_commands_stops_at_first_failure() {
+    let commands = vec![
+      ShellCommand::new("exit 3"),
+      ShellCommand::new("echo unreachable"),
+    ];
+    // commands after the failure are not logged as running
+    let started = std::cell::RefCell::new(vec![]);
+    let report = run_commands(&commands, |command| started.borrow_mut().push(command.command.clone()));
+    assert_eq!(started.into_inner(), vec!["exit 3".to_string()]);
+    assert!(!report.success);
+    assert_eq!(report.results.len(), 1);
+    assert_eq!(report.first_failure().unwrap().exit_code, Some(3));
+  }
+
+  #[test]
+  fn test_run_command_streams_and_keeps_output() {
+    let result = run_command(&ShellCommand::new("echo streamed; echo failed >&2; exit 1"), Output::Stream);
+    assert!(!result.success);
+    assert_eq!(result.stdout, "streamed\n");
+    assert_eq!(result.stderr, "failed\n");
+
+    let result = run_command(&ShellCommand::new("echo x"), Output::Stream);
+    assert!(result.success);
+    assert_eq!(result.stdout.trim(), "x");
+  }
+}
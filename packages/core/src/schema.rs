@@ -0,0 +1,118 @@
+use crate::config::{HookCommand, IgitConfig};
+use schemars::generate::SchemaSettings;
+use schemars::{json_schema, Schema, SchemaGenerator};
+use serde_json::{json, Map};
+
+/// version of the published schema, bump it when the config changes
+pub static SCHEMA_VERSION: &str = "0.0.4";
+
+/// hooks offered for completion, other names are still accepted
+static GIT_HOOKS: &[&str] = &[
+  "applypatch-msg",
+  "pre-applypatch",
+  "post-applypatch",
+  "pre-commit",
+  "pre-merge-commit",
+  "prepare-commit-msg",
+  "commit-msg",
+  "post-commit",
+  "pre-rebase",
+  "post-checkout",
+  "post-merge",
+  "pre-push",
+  "pre-receive",
+  "update",
+  "proc-receive",
+  "post-receive",
+  "post-update",
+  "reference-transaction",
+  "push-to-checkout",
+  "pre-auto-gc",
+  "post-rewrite",
+  "sendemail-validate",
+  "fsmonitor-watchman",
+  "p4-changelist",
+  "p4-prepare-changelist",
+  "p4-post-changelist",
+  "p4-pre-submit",
+  "post-index-change",
+];
+
+pub fn url() -> String {
+  format!("https://igit.erguotou.me/schema/{}/schema.json", SCHEMA_VERSION)
+}
+
+/**
+ * schema of `hooks.hooks`, commands keyed by the name of the git hook
+ */
+pub fn hooks_schema(generator: &mut SchemaGenerator) -> Schema {
+  let command = generator.subschema_for::<HookCommand>();
+  let properties = GIT_HOOKS
+    .iter()
+    .map(|hook| (hook.to_string(), command.clone().to_value()))
+    .collect::<Map<_, _>>();
+  json_schema!({
+    "type": "object",
+    "properties": properties,
+    "additionalProperties": command,
+  })
+}
+
+/**
+ * `pattern` of a list rule can also be written as `patterns`
+ */
+pub fn rule_entry_transform(schema: &mut Schema) {
+  if let Some(pattern) = schema.get("properties").and_then(|p| p.get("pattern")).cloned() {
+    if let Some(properties) = schema.get_mut("properties").and_then(|p| p.as_object_mut()) {
+      properties.insert("patterns".to_string(), pattern);
+    }
+  }
+  schema.insert("required".to_string(), json!(["commands"]));
+  schema.insert("oneOf".to_string(), json!([{ "required": ["pattern"] }, { "required": ["patterns"] }]));
+}
+
+/**
+ * json schema of the config file, as pretty printed json
+ */
+pub fn generate() -> String {
+  let generator = SchemaSettings::draft07().into_generator();
+  let mut schema = generator.into_root_schema_for::<IgitConfig>();
+  // the defaults of whole sections only repeat the defaults of their keys
+  if let Some(properties) = schema.get_mut("properties").and_then(|p| p.as_object_mut()) {
+    for section in properties.values_mut() {
+      if let Some(section) = section.as_object_mut() {
+        section.remove("default");
+      }
+    }
+  }
+  schema.insert("$id".to_string(), url().into());
+  let mut json = serde_json::to_string_pretty(&schema).expect("Failed to serialize the config schema");
+  json.push('\n');
+  json
+}
+
+/* ------------ test ------------ */
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::path::Path;
+
+  #[test]
+  fn test_schema_in_sync() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+      .join("../doc/public/schema")
+      .join(SCHEMA_VERSION)
+      .join("schema.json");
+    let schema = generate();
+    if std::env::var("UPDATE_SCHEMA").is_ok() {
+      std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+      std::fs::write(&path, &schema).unwrap();
+    }
+    let committed = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+      committed == schema,
+      "{} is out of sync with the config types, run `UPDATE_SCHEMA=1 cargo test schema` to update it",
+      path.display()
+    );
+  }
+}
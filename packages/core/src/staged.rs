@@ -0,0 +1,1056 @@
+use crate::command::{
+  chunk_args, chunk_by_cost, default_max_arg_length, has_placeholders, placeholder_cost,
+  quote_path, render_placeholders, runs_per_file, PathContext, ShellCommand,
+};
+use crate::config;
+use crate::config::{IgitConfig, StagedHooksConfig, StagedRuleOptions};
+use crate::hooks::get_commands;
+use crate::log::LOG_PREFIX;
+use crate::runner::{self, CommandResult, Output, RunReport};
+use crate::git::{self, ChangeKind, StagedFile};
+use crate::stash::StagedBackup;
+use fast_glob::glob_match;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+/**
+ * a single run of a command and the number of files it receives
+ */
+#[derive(Debug)]
+pub struct StagedBatch {
+  pub shell_command: ShellCommand,
+  pub files_count: usize,
+}
+
+/**
+ * a configured command split into batches that fit the command line limit
+ */
+#[derive(Debug)]
+pub struct StagedCommand {
+  pub command: String,
+  pub batches: Vec<StagedBatch>,
+}
+
+struct BatchResult {
+  /// the `Running staged command` line of the batch
+  log: String,
+  result: CommandResult,
+}
+
+/**
+ * commands of a single staged rule and the files they run on
+ */
+#[derive(Debug)]
+pub struct StagedTask {
+  pub pattern: String,
+  pub files: Vec<OsString>,
+  pub commands: Vec<StagedCommand>,
+  pub restage: bool,
+}
+
+/**
+ * include and ignore patterns of a rule, `!pattern` entries count as ignores
+ */
+struct FileMatcher {
+  includes: Vec<String>,
+  ignores: Vec<String>,
+  dir: Option<String>,
+}
+
+impl FileMatcher {
+  fn new(patterns: &[String], ignore: &[String], dir: Option<&str>) -> Self {
+    let mut includes = vec![];
+    let mut ignores = ignore.to_vec();
+    for pattern in patterns {
+      match pattern.strip_prefix('!') {
+        Some(negated) => ignores.push(negated.to_string()),
+        None => includes.push(pattern.to_string()),
+      }
+    }
+    let dir = dir
+      .map(|dir| dir.trim_start_matches("./").trim_end_matches('/').to_string())
+      .filter(|dir| !dir.is_empty() && dir != ".");
+    Self {
+      includes,
+      ignores,
+      dir,
+    }
+  }
+
+  fn matches(&self, file: &OsStr) -> bool {
+    let file = git::path_bytes(file);
+    let file = match &self.dir {
+      Some(dir) => match file.strip_prefix(dir.as_bytes()).and_then(|f| f.strip_prefix(b"/")) {
+        Some(file) => file,
+        None => return false,
+      },
+      None => &file,
+    };
+    let included = self.includes.is_empty() || self.includes.iter().any(|p| glob_match(p, file));
+    included && !self.ignores.iter().any(|p| glob_match(p, file))
+  }
+}
+
+/**
+ * glob match
+ */
+fn get_matched_files<'a, T: AsRef<OsStr> + ?Sized>(matcher: &FileMatcher, files: &[&'a T]) -> Vec<&'a T> {
+  let mut matched_files = Vec::new();
+  for file in files.iter() {
+    if matcher.matches(file.as_ref()) {
+      matched_files.push(*file);
+    }
+  }
+  matched_files
+}
+
+fn task_log(command: &str, files_count: usize, pattern: &str) -> String {
+  format!("{}\x1b[90mRunning staged command:\x1b[0m \x1b[32m{}\x1b[0m \x1b[90mfor\x1b[0m \x1b[32m{}\x1b[0m \x1b[90mfiles that match pattern(\x1b[0m\x1b[34m{}\x1b[0m\x1b[90m)\x1b[0m", LOG_PREFIX, command, files_count, pattern)
+}
+
+/**
+ * pass the files as arguments, names that are not valid UTF-8 are quoted into
+ * the command line instead as arguments only hold strings
+ */
+fn command_with_files(command: &str, files: Vec<OsString>) -> ShellCommand {
+  match files.iter().map(|file| file.to_str().map(str::to_string)).collect::<Option<Vec<String>>>() {
+    Some(args) => ShellCommand::with_args(command, args),
+    None => {
+      let files = files.iter().map(|file| quote_path(file)).collect::<Vec<String>>();
+      ShellCommand::new(format!("{} {}", command, files.join(" ")))
+    }
+  }
+}
+
+/**
+ * build the runs of a command for the matched files
+ */
+fn build_batches(
+  command: &str,
+  options: &StagedRuleOptions,
+  files: &[OsString],
+  context: &PathContext,
+  max_arg_length: usize,
+) -> Vec<StagedBatch> {
+  if has_placeholders(command) {
+    let batches = if runs_per_file(command) {
+      files.iter().map(|file| vec![file.clone()]).collect()
+    } else {
+      chunk_by_cost(
+        command.len(),
+        files,
+        |file| placeholder_cost(command, file, context),
+        max_arg_length,
+      )
+    };
+    return batches
+      .into_iter()
+      .map(|files| StagedBatch {
+        shell_command: ShellCommand::new(render_placeholders(command, &files, context)),
+        files_count: files.len(),
+      })
+      .collect();
+  }
+  if !options.pass_files {
+    return vec![StagedBatch {
+      shell_command: ShellCommand::new(command),
+      files_count: 0,
+    }];
+  }
+  let files = files.iter().map(|file| context.rebase(file)).collect::<Vec<OsString>>();
+  chunk_args(command, &files, max_arg_length)
+    .into_iter()
+    .map(|files| StagedBatch {
+      files_count: files.len(),
+      shell_command: command_with_files(command, files),
+    })
+    .collect()
+}
+
+/**
+ * match staged files against the rules, one task per matched rule in execution order
+ */
+pub fn collect_tasks(
+  config: &StagedHooksConfig,
+  staged_files: &[StagedFile],
+  context: &PathContext,
+) -> Vec<StagedTask> {
+  let max_arg_length = config
+    .max_arg_length
+    .unwrap_or_else(default_max_arg_length);
+  let mut tasks = vec![];
+  for rule in config.rules.ordered() {
+    let patterns = rule.pattern.to_vec();
+    let matcher = FileMatcher::new(&patterns, &rule.options.ignore, rule.options.dir.as_deref());
+    let staged_files = staged_files
+      .iter()
+      .filter(|file| match &rule.options.changes {
+        Some(changes) => changes.contains(&file.change),
+        None => file.change != ChangeKind::Deleted,
+      })
+      .map(|file| file.path.as_os_str())
+      .collect::<Vec<&OsStr>>();
+    let matched_files = get_matched_files(&matcher, &staged_files)
+      .iter()
+      .map(|s| s.to_os_string())
+      .collect::<Vec<OsString>>();
+    if matched_files.is_empty() {
+      continue;
+    }
+    let commands = get_commands(&rule.options.commands)
+      .into_iter()
+      .map(|command| StagedCommand {
+        command: command.to_string(),
+        batches: build_batches(command, &rule.options, &matched_files, context, max_arg_length),
+      })
+      .collect();
+    tasks.push(StagedTask {
+      pattern: patterns.join(", "),
+      files: matched_files,
+      commands,
+      restage: rule.options.restage,
+    });
+  }
+  tasks
+}
+
+/**
+ * group tasks that share files into chains, chains are independent of each other
+ */
+fn plan_chains(tasks: &[StagedTask]) -> Vec<Vec<usize>> {
+  let mut parents = (0..tasks.len()).collect::<Vec<usize>>();
+  fn find(parents: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parents[root] != root {
+      root = parents[root];
+    }
+    parents[i] = root;
+    root
+  }
+  for i in 0..tasks.len() {
+    let files = tasks[i].files.iter().collect::<HashSet<&OsString>>();
+    for (j, other) in tasks.iter().enumerate().skip(i + 1) {
+      if other.files.iter().any(|f| files.contains(f)) {
+        let (a, b) = (find(&mut parents, i), find(&mut parents, j));
+        parents[b.max(a)] = a.min(b);
+      }
+    }
+  }
+  let mut chains: Vec<Vec<usize>> = vec![];
+  let mut roots: Vec<usize> = vec![];
+  for i in 0..tasks.len() {
+    let root = find(&mut parents, i);
+    match roots.iter().position(|&r| r == root) {
+      Some(index) => chains[index].push(i),
+      None => {
+        roots.push(root);
+        chains.push(vec![i]);
+      }
+    }
+  }
+  chains
+}
+
+/**
+ * prints the captured output of finished tasks in task order, so logs follow the config
+ * even when rules finish out of order
+ */
+struct OrderedOutput {
+  mode: Output,
+  next: usize,
+  pending: BTreeMap<usize, Vec<BatchResult>>,
+  results: Vec<CommandResult>,
+}
+
+impl OrderedOutput {
+  fn new(mode: Output) -> Self {
+    Self {
+      mode,
+      next: 0,
+      pending: BTreeMap::new(),
+      results: vec![],
+    }
+  }
+
+  fn print(&mut self, results: Vec<BatchResult>) {
+    for batch in results {
+      // streamed batches were logged before they ran
+      if self.mode == Output::Capture {
+        println!("{}", batch.log);
+        runner::print_result(&batch.result);
+      }
+      self.results.push(batch.result);
+    }
+  }
+
+  fn finish(&mut self, index: usize, results: Vec<BatchResult>) {
+    self.pending.insert(index, results);
+    while let Some(results) = self.pending.remove(&self.next) {
+      self.print(results);
+      self.next += 1;
+    }
+  }
+
+  /**
+   * print what is left, tasks skipped after a failure leave gaps
+   */
+  fn flush(mut self) -> Vec<CommandResult> {
+    while let Some((_, results)) = self.pending.pop_first() {
+      self.print(results);
+    }
+    self.results
+  }
+}
+
+/**
+ * run every batch of a command before deciding whether to go on with the next command,
+ * captured batches are logged along with their output once the task is done
+ */
+fn run_task(task: &StagedTask, output: Output) -> Vec<BatchResult> {
+  let mut results = vec![];
+  for command in task.commands.iter() {
+    let mut success = true;
+    for batch in command.batches.iter() {
+      let log = task_log(&command.command, batch.files_count, &task.pattern);
+      if output == Output::Stream {
+        println!("{}", log);
+      }
+      let result = runner::run_command(&batch.shell_command, output);
+      success = success && result.success;
+      results.push(BatchResult { log, result });
+    }
+    if !success {
+      break;
+    }
+  }
+  results
+}
+
+/**
+ * run tasks, tasks sharing files run in order and the rest run in parallel
+ */
+pub fn run_tasks(tasks: &[StagedTask], concurrency: usize) -> RunReport {
+  let start = Instant::now();
+  let chains = plan_chains(tasks);
+  let queue = Mutex::new(chains.iter().collect::<VecDeque<_>>());
+  let failed = AtomicBool::new(false);
+  let workers = concurrency.max(1).min(chains.len());
+  // output of parallel rules is held back so every rule prints as one block
+  let mode = if workers > 1 { Output::Capture } else { Output::Stream };
+  let output = Mutex::new(OrderedOutput::new(mode));
+
+  thread::scope(|scope| {
+    for _ in 0..workers {
+      scope.spawn(|| loop {
+        if failed.load(Ordering::SeqCst) {
+          break;
+        }
+        let next = queue.lock().unwrap_or_else(|e| e.into_inner()).pop_front();
+        let Some(chain) = next else {
+          break;
+        };
+        for &task_index in chain.iter() {
+          let results = run_task(&tasks[task_index], mode);
+          let success = results.iter().all(|r| r.result.success);
+          output
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .finish(task_index, results);
+          if !success {
+            failed.store(true, Ordering::SeqCst);
+            break;
+          }
+        }
+      });
+    }
+  });
+
+  let mut report = RunReport::default();
+  let output = output.into_inner().unwrap_or_else(|e| e.into_inner());
+  for result in output.flush() {
+    report.push(result);
+  }
+  report.duration_ms = runner::elapsed_ms(start);
+  report
+}
+
+fn hash_file(file: &Path) -> Option<u64> {
+  let content = fs::read(file).ok()?;
+  let mut hasher = DefaultHasher::new();
+  content.hash(&mut hasher);
+  Some(hasher.finish())
+}
+
+/**
+ * content hashes of the files that may be re-staged
+ */
+fn hash_restage_files(tasks: &[StagedTask], root: &Path) -> HashMap<OsString, Option<u64>> {
+  tasks
+    .iter()
+    .filter(|task| task.restage)
+    .flat_map(|task| task.files.iter())
+    .map(|file| (file.clone(), hash_file(&root.join(file))))
+    .collect()
+}
+
+/**
+ * add files whose content changed since `hashes` were taken back to the index
+ */
+fn restage_modified_files(hashes: &HashMap<OsString, Option<u64>>, root: &Path) -> Result<Vec<OsString>, String> {
+  let mut modified = hashes
+    .iter()
+    .filter(|(file, hash)| hash_file(&root.join(file)) != **hash)
+    .map(|(file, _)| file.clone())
+    .collect::<Vec<OsString>>();
+  if modified.is_empty() {
+    return Ok(modified);
+  }
+  modified.sort();
+  // file names are passed as they are, not as glob patterns
+  let mut args = ["--literal-pathspecs", "add", "--"].map(OsStr::new).to_vec();
+  args.extend(modified.iter().map(|s| s.as_os_str()));
+  git::run_in(root, &args)?;
+  println!(
+    "{}\x1b[90mRe-staged\x1b[0m \x1b[32m{}\x1b[0m \x1b[90mfiles modified by staged commands\x1b[0m",
+    LOG_PREFIX,
+    modified.len()
+  );
+  Ok(modified)
+}
+
+fn default_concurrency() -> usize {
+  thread::available_parallelism()
+    .map(|n| n.get())
+    .unwrap_or(1)
+}
+
+/**
+ * nearest directory above the file that holds its own config, empty when the
+ * file belongs to the repository config. a `.config` directory only holds the
+ * config of its parent, so it is never a package itself
+ */
+fn owning_package(file: &OsStr, root: &Path, has_config: &mut HashMap<OsString, bool>) -> OsString {
+  let file = git::path_bytes(file);
+  let mut end = file.len();
+  while let Some(index) = file[..end].iter().rposition(|&b| b == b'/') {
+    end = index;
+    if file[..end].rsplit(|&b| b == b'/').next() == Some(b".config") {
+      continue;
+    }
+    let dir = git::path_from_bytes(&file[..end]);
+    let found = *has_config
+      .entry(dir.clone())
+      .or_insert_with(|| config::config_file_in(&root.join(&dir)).is_some());
+    if found {
+      return dir;
+    }
+  }
+  OsString::new()
+}
+
+/**
+ * config of the repository root, used for files outside of packages and for run settings
+ */
+fn repo_config(root: &Path) -> Result<Option<IgitConfig>, String> {
+  if config::find_config_file_from(root).is_none() {
+    return Ok(None);
+  }
+  config::parse_from(root).map(Some)
+}
+
+fn has_staged_rules(config: &IgitConfig) -> bool {
+  config.staged_hooks.enabled && !config.staged_hooks.rules.is_empty()
+}
+
+/**
+ * group staged files by the config owning them, rules of a package config only
+ * see the files under the package and its commands run in the package directory
+ */
+fn collect_scoped_tasks(
+  repo_config: Option<&IgitConfig>,
+  staged_files: &[StagedFile],
+  root: &Path,
+  cwd: &Path,
+) -> Result<Vec<StagedTask>, String> {
+  let mut has_config = HashMap::new();
+  let mut packages: BTreeMap<OsString, Vec<StagedFile>> = BTreeMap::new();
+  for file in staged_files {
+    let dir = owning_package(&file.path, root, &mut has_config);
+    let path = if dir.is_empty() {
+      file.path.clone()
+    } else {
+      git::path_from_bytes(&git::path_bytes(&file.path)[git::path_bytes(&dir).len() + 1..])
+    };
+    packages.entry(dir).or_default().push(StagedFile {
+      path,
+      change: file.change,
+    });
+  }
+
+  let mut tasks = vec![];
+  for (dir, files) in packages {
+    if dir.is_empty() {
+      let Some(config) = repo_config.filter(|config| has_staged_rules(config)) else {
+        continue;
+      };
+      let context = PathContext { root, cwd };
+      tasks.extend(collect_tasks(&config.staged_hooks, &files, &context));
+      continue;
+    }
+    let package_dir = root.join(&dir);
+    let Some(config_file) = config::config_file_in(&package_dir) else {
+      continue;
+    };
+    let config = config::parse_file(&config_file)?;
+    if !has_staged_rules(&config) {
+      continue;
+    }
+    if !config.staged_hooks.stash || config.staged_hooks.concurrency.is_some() {
+      println!(
+        "{}\x1b[33m`staged_hooks.stash` and `staged_hooks.concurrency` of {} are ignored, they are only read from the repository config\x1b[0m",
+        LOG_PREFIX,
+        config_file.display()
+      );
+    }
+    let context = PathContext {
+      root: &package_dir,
+      cwd: &package_dir,
+    };
+    let package_cwd = package_dir.to_string_lossy().to_string();
+    for mut task in collect_tasks(&config.staged_hooks, &files, &context) {
+      task.pattern = format!("{}: {}", dir.to_string_lossy(), task.pattern);
+      task.files = task
+        .files
+        .iter()
+        .map(|file| {
+          let mut path = dir.clone();
+          path.push("/");
+          path.push(file);
+          path
+        })
+        .collect();
+      for command in task.commands.iter_mut() {
+        for batch in command.batches.iter_mut() {
+          batch.shell_command.cwd = Some(package_cwd.to_string());
+        }
+      }
+      tasks.push(task);
+    }
+  }
+  Ok(tasks)
+}
+
+/**
+ * collect staged commands
+ */
+pub fn collect_staged_commands() -> Result<Vec<ShellCommand>, Box<dyn Error>> {
+  config::check_git()?;
+  let staged_files = git::staged_files()?;
+  let root = git::root()?;
+  let cwd = std::env::current_dir()?;
+  let repo_config = repo_config(&root)?;
+  let mut staged_commands: Vec<ShellCommand> = vec![];
+  for task in collect_scoped_tasks(repo_config.as_ref(), &staged_files, &root, &cwd)? {
+    for command in task.commands {
+      for batch in command.batches {
+        println!("{}", task_log(&command.command, batch.files_count, &task.pattern));
+        staged_commands.push(batch.shell_command);
+      }
+    }
+  }
+  Ok(staged_commands)
+}
+
+/**
+ * run staged commands
+ */
+pub fn run_staged_commands() -> Result<RunReport, Box<dyn Error>> {
+  config::check_git()?;
+  let staged_files = git::staged_files()?;
+  let root = git::root()?;
+  let cwd = std::env::current_dir()?;
+  let repo_config = repo_config(&root)?;
+  let tasks = collect_scoped_tasks(repo_config.as_ref(), &staged_files, &root, &cwd)?;
+  if tasks.is_empty() {
+    return Ok(RunReport::default());
+  }
+  let settings = repo_config.map(|config| config.staged_hooks);
+  let backup = if settings.as_ref().is_none_or(|settings| settings.stash) {
+    let paths = staged_files
+      .iter()
+      .map(|file| file.path.clone())
+      .collect::<Vec<OsString>>();
+    StagedBackup::create(&paths)?
+  } else {
+    None
+  };
+  let concurrency = settings
+    .and_then(|settings| settings.concurrency)
+    .unwrap_or_else(default_concurrency);
+  let hashes = hash_restage_files(&tasks, &root);
+  let report = run_tasks(&tasks, concurrency);
+  if report.success {
+    if let Err(e) = restage_modified_files(&hashes, &root) {
+      return Err(match &backup {
+        Some(backup) => backup.keep(e),
+        None => e,
+      }
+      .into());
+    }
+    if let Some(backup) = backup {
+      backup.restore()?;
+    }
+  } else if let Some(backup) = backup {
+    backup.rollback()?;
+  }
+  Ok(report)
+}
+
+/* ------------ test ------------ */
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::path::Path;
+
+  fn context() -> PathContext<'static> {
+    PathContext {
+      root: Path::new("/repo"),
+      cwd: Path::new("/repo"),
+    }
+  }
+
+  fn batch(command: &str) -> StagedBatch {
+    StagedBatch {
+      shell_command: ShellCommand::new(command),
+      files_count: 0,
+    }
+  }
+
+  fn command(command: &str, batches: Vec<StagedBatch>) -> StagedCommand {
+    StagedCommand {
+      command: command.to_string(),
+      batches,
+    }
+  }
+
+  fn task(pattern: &str, files: &[&str], command_line: &str) -> StagedTask {
+    StagedTask {
+      pattern: pattern.to_string(),
+      files: files.iter().map(OsString::from).collect(),
+      commands: vec![command(command_line, vec![batch(command_line)])],
+      restage: true,
+    }
+  }
+
+  fn staged(paths: &[&str]) -> Vec<StagedFile> {
+    paths
+      .iter()
+      .map(|path| StagedFile {
+        path: path.into(),
+        change: ChangeKind::Modified,
+      })
+      .collect()
+  }
+
+  fn match_pattern<'a>(pattern: &str, files: &[&'a str]) -> Vec<&'a str> {
+    get_matched_files(&FileMatcher::new(&[pattern.to_string()], &[], None), files)
+  }
+
+  fn batch_lines(task: &StagedTask) -> Vec<String> {
+    task
+      .commands
+      .iter()
+      .flat_map(|command| command.batches.iter())
+      .map(|batch| batch.shell_command.to_command_line())
+      .collect()
+  }
+
+  #[test]
+  fn test_get_matched_files() {
+    let files = vec![
+      "foo/bar/baz.css",
+      "src/main.rs",
+      "src/lib.rs",
+      "tests/test.rs",
+      "README.md",
+    ];
+
+    let matched = match_pattern("**/*.rs", &files);
+    assert_eq!(matched.len(), 3);
+    assert!(matched.contains(&"src/main.rs"));
+    assert!(matched.contains(&"src/lib.rs"));
+    assert!(matched.contains(&"tests/test.rs"));
+
+    let matched = match_pattern("**/*.{rs,md}", &files);
+    assert_eq!(matched.len(), 4);
+    assert!(matched.contains(&"src/main.rs"));
+    assert!(matched.contains(&"src/lib.rs"));
+    assert!(matched.contains(&"tests/test.rs"));
+    assert!(matched.contains(&"README.md"));
+
+    let matched = match_pattern("*/*.css", &files);
+    assert_eq!(matched.len(), 0);
+
+    let matched = match_pattern("!**/*.rs", &files);
+    assert_eq!(matched.len(), 2);
+    assert!(matched.contains(&"foo/bar/baz.css"));
+    assert!(matched.contains(&"README.md"));
+  }
+
+  #[test]
+  fn test_file_matcher_with_ignores_and_dir() {
+    let files = vec![
+      "packages/app/src/main.ts",
+      "packages/app/src/fixtures/data.ts",
+      "packages/app/vendor/lib.ts",
+      "packages/app/README.md",
+      "packages/web/src/main.ts",
+      "src/main.ts",
+    ];
+    let matcher = FileMatcher::new(
+      &["src/**/*.ts".to_string(), "!src/fixtures/**".to_string()],
+      &["vendor/**".to_string()],
+      Some("./packages/app/"),
+    );
+    assert_eq!(
+      get_matched_files(&matcher, &files),
+      vec!["packages/app/src/main.ts"]
+    );
+
+    let matcher = FileMatcher::new(
+      &["**/*.ts".to_string(), "**/*.md".to_string()],
+      &["**/vendor/**".to_string(), "**/fixtures/**".to_string()],
+      None,
+    );
+    assert_eq!(
+      get_matched_files(&matcher, &files),
+      vec![
+        "packages/app/src/main.ts",
+        "packages/app/README.md",
+        "packages/web/src/main.ts",
+        "src/main.ts",
+      ]
+    );
+  }
+
+  #[test]
+  fn test_collect_tasks_with_rule_options() {
+    let config: StagedHooksConfig = serde_yaml::from_str(
+      "enabled: true
+rules:
+  '**/*.ts': biome check --write
+  '**/*.css':
+    commands:
+      - stylelint
+    restage: false
+",
+    )
+    .unwrap();
+    let files = staged(&["a.ts", "b.css"]);
+    let tasks = collect_tasks(&config, &files, &context());
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(batch_lines(&tasks[0]), vec!["biome check --write a.ts"]);
+    assert!(tasks[0].restage);
+    assert_eq!(tasks[1].pattern, "**/*.css");
+    assert_eq!(batch_lines(&tasks[1]), vec!["stylelint b.css"]);
+    assert!(!tasks[1].restage);
+  }
+
+  #[test]
+  fn test_collect_tasks_keeps_config_order() {
+    let config: StagedHooksConfig = serde_yaml::from_str(
+      "enabled: true
+rules:
+  'z/**': echo z
+  'a/**': echo a
+  'm/**': echo m
+",
+    )
+    .unwrap();
+    let files = staged(&["a/1", "m/1", "z/1"]);
+    let patterns = collect_tasks(&config, &files, &context())
+      .into_iter()
+      .map(|task| task.pattern)
+      .collect::<Vec<String>>();
+    assert_eq!(patterns, vec!["z/**", "a/**", "m/**"]);
+  }
+
+  #[test]
+  fn test_collect_tasks_from_list_with_priority() {
+    let config: StagedHooksConfig = serde_yaml::from_str(
+      "enabled: true
+rules:
+  - pattern: '**/*.ts'
+    commands: eslint
+  - pattern: '**/*.{ts,css}'
+    commands: prettier --write
+    priority: -1
+  - pattern: '**/*.css'
+    commands: stylelint
+",
+    )
+    .unwrap();
+    let files = staged(&["a.ts", "b.css"]);
+    let commands = collect_tasks(&config, &files, &context())
+      .into_iter()
+      .flat_map(|task| batch_lines(&task))
+      .collect::<Vec<String>>();
+    assert_eq!(
+      commands,
+      vec!["prettier --write a.ts b.css", "eslint a.ts", "stylelint b.css"]
+    );
+  }
+
+  #[test]
+  fn test_collect_tasks_with_multiple_patterns() {
+    let config: StagedHooksConfig = serde_yaml::from_str(
+      "enabled: true
+rules:
+  - patterns: ['src/**/*.ts', 'src/**/*.tsx']
+    ignore: ['**/fixtures/**']
+    dir: packages/app
+    commands: eslint
+",
+    )
+    .unwrap();
+    let files = staged(&[
+      "packages/app/src/a.ts",
+      "packages/app/src/b.tsx",
+      "packages/app/src/fixtures/c.ts",
+      "src/d.ts",
+    ]);
+    let tasks = collect_tasks(&config, &files, &context());
+    assert_eq!(tasks[0].pattern, "src/**/*.ts, src/**/*.tsx");
+    assert_eq!(
+      batch_lines(&tasks[0]),
+      vec!["eslint packages/app/src/a.ts packages/app/src/b.tsx"]
+    );
+  }
+
+  #[test]
+  fn test_collect_tasks_filters_change_kinds() {
+    let config: StagedHooksConfig = serde_yaml::from_str(
+      "enabled: true
+rules:
+  - pattern: '**/*.ts'
+    commands: eslint
+  - pattern: '**/*.ts'
+    commands: echo added
+    changes: [added, renamed]
+  - pattern: '**/*.ts'
+    commands: echo removed
+    changes: [deleted]
+",
+    )
+    .unwrap();
+    let files = vec![
+      StagedFile {
+        path: "a.ts".into(),
+        change: ChangeKind::Added,
+      },
+      StagedFile {
+        path: "b.ts".into(),
+        change: ChangeKind::Modified,
+      },
+      StagedFile {
+        path: "c.ts".into(),
+        change: ChangeKind::Deleted,
+      },
+      StagedFile {
+        path: "d e.ts".into(),
+        change: ChangeKind::Renamed,
+      },
+    ];
+    let tasks = collect_tasks(&config, &files, &context());
+    let lines = tasks.iter().flat_map(batch_lines).collect::<Vec<String>>();
+    assert_eq!(
+      lines,
+      vec!["eslint a.ts b.ts 'd e.ts'", "echo added a.ts 'd e.ts'", "echo removed c.ts"]
+    );
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_collect_tasks_keeps_non_utf8_paths() {
+    use std::os::unix::ffi::OsStrExt;
+    let config: StagedHooksConfig =
+      serde_yaml::from_str("enabled: true\nrules:\n  '**/*.ts':\n    - eslint\n    - prettier {files}\n").unwrap();
+    let files = vec![StagedFile {
+      path: OsStr::from_bytes(b"src/\xff.ts").into(),
+      change: ChangeKind::Modified,
+    }];
+    let tasks = collect_tasks(&config, &files, &context());
+    assert_eq!(tasks[0].files, vec![OsStr::from_bytes(b"src/\xff.ts")]);
+    assert_eq!(
+      batch_lines(&tasks[0]),
+      vec!["eslint \"$(printf 'src/\\377.ts')\"", "prettier \"$(printf 'src/\\377.ts')\""]
+    );
+  }
+
+  #[test]
+  fn test_collect_tasks_with_placeholders() {
+    let config: StagedHooksConfig = serde_yaml::from_str(
+      "enabled: true
+rules:
+  '**/*.ts':
+    - prettier --write {files} --log-level warn
+    - node check.js {file}
+  '**/*.tsx':
+    commands: tsc --noEmit -p .
+    passFiles: false
+",
+    )
+    .unwrap();
+    let files = staged(&["a.ts", "b.ts", "c.tsx"]);
+    let tasks = collect_tasks(&config, &files, &context());
+    assert_eq!(
+      batch_lines(&tasks[0]),
+      vec![
+        "prettier --write a.ts b.ts --log-level warn",
+        "node check.js a.ts",
+        "node check.js b.ts",
+      ]
+    );
+    assert_eq!(batch_lines(&tasks[1]), vec!["tsc --noEmit -p ."]);
+  }
+
+  #[test]
+  fn test_collect_tasks_rebases_files_to_cwd() {
+    let config: StagedHooksConfig = serde_yaml::from_str(
+      "enabled: true
+rules:
+  '**/*.ts':
+    - eslint
+    - prettier --write {files}
+",
+    )
+    .unwrap();
+    let context = PathContext {
+      root: Path::new("/repo"),
+      cwd: Path::new("/repo/packages/app"),
+    };
+    let files = staged(&["packages/app/src/a.ts", "scripts/b.ts"]);
+    let tasks = collect_tasks(&config, &files, &context);
+    assert_eq!(tasks[0].files, vec!["packages/app/src/a.ts", "scripts/b.ts"]);
+    assert_eq!(
+      batch_lines(&tasks[0]),
+      vec![
+        "eslint src/a.ts ../../scripts/b.ts",
+        "prettier --write src/a.ts ../../scripts/b.ts",
+      ]
+    );
+  }
+
+  #[test]
+  fn test_collect_scoped_tasks_by_package() {
+    let root = std::env::temp_dir().join("igit_scoped_tasks");
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("packages/app/.config")).unwrap();
+    fs::create_dir_all(root.join("packages/lib")).unwrap();
+    fs::write(
+      root.join("packages/app/.config/igit.yaml"),
+      "staged_hooks:\n  enabled: true\n  rules:\n    'src/**/*.ts': biome check\n",
+    )
+    .unwrap();
+    let repo_config: IgitConfig =
+      serde_yaml::from_str("staged_hooks:\n  enabled: true\n  rules:\n    '**/*.ts': eslint\n").unwrap();
+    let files = staged(&["packages/app/src/a.ts", "packages/app/b.ts", "packages/lib/c.ts"]);
+    let tasks = collect_scoped_tasks(Some(&repo_config), &files, &root, &root).unwrap();
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0].pattern, "**/*.ts");
+    assert_eq!(tasks[0].files, vec!["packages/lib/c.ts"]);
+    assert_eq!(batch_lines(&tasks[0]), vec!["eslint packages/lib/c.ts"]);
+    assert_eq!(tasks[0].commands[0].batches[0].shell_command.cwd, None);
+    assert_eq!(tasks[1].pattern, "packages/app: src/**/*.ts");
+    assert_eq!(tasks[1].files, vec!["packages/app/src/a.ts"]);
+    assert_eq!(batch_lines(&tasks[1]), vec!["biome check src/a.ts"]);
+    assert_eq!(
+      tasks[1].commands[0].batches[0].shell_command.cwd,
+      Some(root.join("packages/app").to_string_lossy().to_string())
+    );
+
+    // a `.config` directory belongs to its parent
+    fs::create_dir_all(root.join(".config")).unwrap();
+    fs::write(root.join(".config/igit.yaml"), "staged_hooks:\n  enabled: true\n").unwrap();
+    let mut has_config = HashMap::new();
+    let owner = |file: &str, has_config: &mut HashMap<OsString, bool>| owning_package(OsStr::new(file), &root, has_config);
+    assert_eq!(owner(".config/igit.yaml", &mut has_config), "");
+    assert_eq!(owner(".config/tools/lint.ts", &mut has_config), "");
+    assert_eq!(owner("packages/app/.config/igit.yaml", &mut has_config), "packages/app");
+    assert_eq!(owner("packages/lib/src/c.ts", &mut has_config), "");
+    fs::remove_dir_all(&root).unwrap();
+  }
+
+  #[test]
+  fn test_run_task_runs_all_batches_and_sums_failures() {
+    let task = StagedTask {
+      pattern: "**/*".to_string(),
+      files: vec!["a".into(), "b".into(), "c".into()],
+      commands: vec![
+        command(
+          "exit 1",
+          vec![batch("exit 1"), batch("true"), batch("exit 1")],
+        ),
+        command("true", vec![batch("true")]),
+      ],
+      restage: true,
+    };
+    let results = run_task(&task, Output::Capture);
+    assert_eq!(results.len(), 3);
+    assert_eq!(results.iter().filter(|r| !r.result.success).count(), 2);
+  }
+
+  #[test]
+  fn test_plan_chains_groups_overlapping_tasks() {
+    let tasks = vec![
+      task("**/*.ts", &["a.ts", "b.ts"], "true"),
+      task("**/*.css", &["a.css"], "true"),
+      task("b.*", &["b.ts", "b.rs"], "true"),
+      task("**/*.rs", &["b.rs"], "true"),
+    ];
+    let chains = plan_chains(&tasks);
+    assert_eq!(chains, vec![vec![0, 2, 3], vec![1]]);
+  }
+
+  #[test]
+  fn test_run_tasks_reports_in_task_order() {
+    let tasks = vec![
+      task("**/*.ts", &["a.ts"], "sleep 0.2; echo ts"),
+      task("**/*.css", &["a.css"], "echo css"),
+    ];
+    let report = run_tasks(&tasks, 2);
+    assert!(report.success);
+    assert_eq!(report.results.len(), 2);
+    assert_eq!(report.results[0].stdout.trim(), "ts");
+    assert_eq!(report.results[1].stdout.trim(), "css");
+  }
+
+  #[test]
+  fn test_run_task_keeps_the_log_with_captured_output() {
+    let results = run_task(&task("**/*.ts", &["a.ts"], "echo linted"), Output::Capture);
+    assert_eq!(results.len(), 1);
+    assert!(results[0].log.contains("echo linted"));
+    assert_eq!(results[0].result.stdout.trim(), "linted");
+  }
+
+  #[test]
+  fn test_run_tasks_reports_wall_clock_duration() {
+    let tasks = vec![
+      task("**/*.ts", &["a.ts"], "sleep 1"),
+      task("**/*.css", &["a.css"], "sleep 1"),
+    ];
+    let report = run_tasks(&tasks, 2);
+    // the two sleeps overlap, run one after the other they would take 2s
+    assert!(report.duration_ms >= 1000);
+    assert!(report.duration_ms < 1800, "{}", report.duration_ms);
+  }
+}
@@ -0,0 +1,192 @@
+use crate::git;
+use crate::log::LOG_PREFIX;
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::PathBuf;
+
+static BACKUP_MESSAGE: &str = "igit automatic backup";
+static PATCH_FILE: &str = "igit_unstaged.patch";
+static MERGE_FILES: &[&str] = &["MERGE_HEAD", "MERGE_MODE", "MERGE_MSG"];
+
+/**
+ * backup of the index and working tree taken before running staged hooks,
+ * works like lint-staged: partially staged files get their unstaged hunks hidden
+ * while the commands run and restored afterwards
+ */
+pub struct StagedBackup {
+  hash: String,
+  root: PathBuf,
+  patch_path: PathBuf,
+  partial_files: Vec<OsString>,
+  merge_state: Vec<(PathBuf, Vec<u8>)>,
+}
+
+fn get_partially_staged_files(staged_files: &[OsString]) -> Result<Vec<OsString>, String> {
+  let unstaged = git::unstaged_files()?;
+  let unstaged = unstaged.iter().map(|s| s.as_os_str()).collect::<HashSet<&OsStr>>();
+  Ok(
+    staged_files
+      .iter()
+      .filter(|file| unstaged.contains(file.as_os_str()))
+      .cloned()
+      .collect(),
+  )
+}
+
+fn read_merge_state(git_dir: &std::path::Path) -> Vec<(PathBuf, Vec<u8>)> {
+  MERGE_FILES
+    .iter()
+    .map(|name| git_dir.join(name))
+    .filter_map(|path| fs::read(&path).ok().map(|content| (path, content)))
+    .collect()
+}
+
+impl StagedBackup {
+  /**
+   * backup everything and hide the unstaged hunks of partially staged files,
+   * returns `None` when there is nothing to backup (e.g. no commit yet)
+   */
+  pub fn create(staged_files: &[OsString]) -> Result<Option<Self>, String> {
+    if !git::has_head() {
+      return Ok(None);
+    }
+    let git_dir = git::git_dir()?;
+    let root = git::root()?;
+    let partial_files = get_partially_staged_files(staged_files)?;
+    let merge_state = read_merge_state(&git_dir);
+    let hash = git::run(["stash", "create"])?;
+    if hash.is_empty() {
+      return Ok(None);
+    }
+    // keep the backup reachable from the stash list until we are done
+    git::run(["stash", "store", "--quiet", "-m", BACKUP_MESSAGE, &hash])?;
+
+    let backup = StagedBackup {
+      hash,
+      root,
+      patch_path: git_dir.join(PATCH_FILE),
+      partial_files,
+      merge_state,
+    };
+    if backup.partial_files.is_empty() {
+      return Ok(Some(backup));
+    }
+    let mut output = OsString::from("--output=");
+    output.push(&backup.patch_path);
+    // file names are passed as they are, not as glob patterns
+    let mut args = [
+      "--literal-pathspecs",
+      "diff",
+      "--binary",
+      "--unified=0",
+      "--no-color",
+      "--no-ext-diff",
+      "--src-prefix=a/",
+      "--dst-prefix=b/",
+      "--patch",
+      "--submodule=short",
+    ]
+    .map(OsStr::new)
+    .to_vec();
+    args.push(&output);
+    args.push(OsStr::new("--"));
+    args.extend(backup.partial_files.iter().map(|s| s.as_os_str()));
+    if let Err(e) = git::run_in(&backup.root, &args) {
+      // nothing was touched yet, the stash entry is not needed anymore
+      let _ = backup.cleanup();
+      return Err(format!("Failed to save unstaged changes: {}", e));
+    }
+    let mut args = ["--literal-pathspecs", "checkout", "--force", "--"].map(OsStr::new).to_vec();
+    args.extend(backup.partial_files.iter().map(|s| s.as_os_str()));
+    if let Err(e) = git::run_in(&backup.root, &args) {
+      let error = format!("Failed to hide unstaged changes: {}", e);
+      if let Err(reset_error) = backup.reset() {
+        return Err(backup.keep(format!("{}, {}", error, reset_error)));
+      }
+      let _ = backup.cleanup();
+      return Err(error);
+    }
+    println!(
+      "{}\x1b[90mHid unstaged changes in\x1b[0m \x1b[32m{}\x1b[0m \x1b[90mpartially staged files\x1b[0m",
+      LOG_PREFIX,
+      backup.partial_files.len()
+    );
+    Ok(Some(backup))
+  }
+
+  /**
+   * bring back the hidden unstaged hunks and drop the backup
+   */
+  pub fn restore(self) -> Result<(), String> {
+    if !self.partial_files.is_empty() {
+      let patch = self.patch_path.to_string_lossy().to_string();
+      let applied = git::run_in(&self.root, [
+        "apply",
+        "-v",
+        "--whitespace=nowarn",
+        "--recount",
+        "--unidiff-zero",
+        &patch,
+      ])
+      .or_else(|_| {
+        git::run_in(&self.root, [
+          "apply",
+          "-v",
+          "--whitespace=nowarn",
+          "--recount",
+          "--unidiff-zero",
+          "--3way",
+          &patch,
+        ])
+      });
+      if let Err(e) = applied {
+        return Err(self.keep(format!("Failed to restore unstaged changes: {}", e)));
+      }
+    }
+    self.cleanup()
+  }
+
+  /**
+   * reset the index and working tree to the state before the staged hooks ran
+   */
+  pub fn rollback(self) -> Result<(), String> {
+    println!(
+      "{}\x1b[31mStaged hooks failed, restoring the original state\x1b[0m",
+      LOG_PREFIX
+    );
+    if let Err(e) = self.reset() {
+      return Err(self.keep(e));
+    }
+    self.cleanup()
+  }
+
+  /**
+   * tell how to get the backup back after a failed step, the stash entry is kept
+   */
+  pub fn keep(&self, error: String) -> String {
+    println!(
+      "{}\x1b[33mThe original index and working tree are kept in the stash `{}` ({}), recover them with `git reset --hard && git stash apply --index {}`\x1b[0m",
+      LOG_PREFIX, BACKUP_MESSAGE, self.hash, self.hash
+    );
+    error
+  }
+
+  fn reset(&self) -> Result<(), String> {
+    git::run(["reset", "--hard", "--quiet", "HEAD"])?;
+    git::run(["stash", "apply", "--quiet", "--index", &self.hash])?;
+    for (path, content) in self.merge_state.iter() {
+      fs::write(path, content).map_err(|e| format!("Failed to restore {}: {}", path.display(), e))?;
+    }
+    Ok(())
+  }
+
+  fn cleanup(&self) -> Result<(), String> {
+    let _ = fs::remove_file(&self.patch_path);
+    let hashes = git::run(["stash", "list", "--format=%H"])?;
+    if let Some(index) = hashes.lines().position(|hash| hash == self.hash) {
+      git::run(["stash", "drop", "--quiet", &format!("stash@{{{}}}", index)])?;
+    }
+    Ok(())
+  }
+}